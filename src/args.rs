@@ -4,11 +4,24 @@
 use std::convert::TryFrom;
 use std::path::{Path, PathBuf};
 
-use getopts::Matches;
+use super::{
+    codegen_options::{
+        CodegenOptions, DebugInfo, Lto, OptLevel, PanicStrategy, RelocModel, SplitDebuginfo, Strip,
+    },
+    dependencies::{self, Dependency, LinkInfo},
+    env_args::EnvArgs,
+    lints,
+    outputs::OutputFilenames,
+    rustc_args::{
+        CrateType, Edition, EmitKind, LinkModifier, NativeLib, NativeLibKind, RustcInvocation,
+        SearchPath, SearchPathKind,
+    },
+    sanitizers,
+    target::Target,
+    target_features, Error, Result,
+};
 
-use super::{env_args::EnvArgs, rustc_args::RustcArgs, Error, Result};
-
-/// A collection containing multiple instances of `Args`. This is necessary in order
+/// A plan to mimic a single `rustc` invokation using `gccrs`. This is necessary in order
 /// to circumvent the fact that `rustc` can currently generate multiple types of binaries
 /// with a single invokation.
 ///
@@ -22,195 +35,1416 @@ use super::{env_args::EnvArgs, rustc_args::RustcArgs, Error, Result};
 /// Let's look at the previous example:
 ///
 /// * To generate an executable from `src/<file>.rs`,
-/// we'd need to do the following: `gccrs src/<file>.rs` (with `-o <file>` if we don't
-/// want an executable named a.out, but that's not important).
+///   we'd need to do the following: `gccrs src/<file>.rs` (with `-o <file>` if we don't
+///   want an executable named a.out, but that's not important).
 ///
 /// * For a shared library, we need to add the `-shared` flag. On top of this, `rustc`
-/// generates libraries named `lib<name>.[so|a]` on Linux, while `gcc` will happily generate
-/// a shared library without any extension or prefix. This amounts to the following command:
-/// `gccrs -shared src/<file>.rs -o lib<file>.so`.
+///   generates libraries named `lib<name>.[so|a]` on Linux, while `gcc` will happily generate
+///   a shared library without any extension or prefix. This amounts to the following command:
+///   `gccrs -shared src/<file>.rs -o lib<file>.so`.
 ///
 /// * Finally, `gcc` is not able to generate a static library at all. We *need* to use a
-/// different command, `ar`, in order to bundle up object files previously created by
-/// `gcc`. Therefore, we actually need *two* commands:
-/// `gccrs -c src/<file>.rs && ar csr src/<file>.o`
-///
-/// Multiple flags, such as `shared`, conflict with the generation of other binaries. On
-/// top of that, we cannot use the `-o` option precisely enough to control the output name
-/// based on the binary file produced.
+///   different command, `ar`, in order to bundle up object files previously created by
+///   `gcc`. Therefore, we actually need *two* commands:
+///   `gccrs -c src/<file>.rs && ar csr src/<file>.o`
 ///
-/// Therefore, we need to wrap an unknown amount of sets of `gccrs` arguments in order to
-/// mimic a single `rustc` invokation. Later on, we need to iterate over those sets and
-/// spawn a new `gccrs` command for each of them.
+/// Compiling the crate once per binary would parse, type-check and generate code for the
+/// same crate multiple times, and report each diagnostic as many times. Instead, the
+/// crate is compiled once to a position independant object file, which is then used to
+/// produce every [`Artifact`]: `ar` creates the archives, and `gccrs` links the shared
+/// libraries and executables.
 pub struct ArgsCollection {
-    args_set: Vec<Args>,
+    args: Args,
+    artifacts: Vec<Artifact>,
+    outputs: OutputFilenames,
 }
 
-/// Get the corresponding set of `gccrs` arguments from a single set of `rustc` arguments
-impl TryFrom<&RustcArgs> for ArgsCollection {
-    type Error = Error;
+impl ArgsCollection {
+    /// Get the corresponding plan of `gccrs` arguments from a single `rustc` invocation,
+    /// producing binaries for the given target
+    pub fn new(invocation: &RustcInvocation, target: &Target) -> Result<ArgsCollection> {
+        // Without a harness, a test crate would be built as a regular crate: Binaries
+        // would run their `main` function as the tests, and libraries would not link
+        if invocation.test {
+            return Err(Error::TestHarness);
+        }
 
-    fn try_from(rustc_args: &RustcArgs) -> Result<ArgsCollection> {
-        let matches = rustc_args.matches();
+        let dependencies = invocation
+            .externs
+            .iter()
+            .map(Dependency::try_from)
+            .collect::<Result<Vec<Dependency>>>()?;
+        let outputs = OutputFilenames::new(invocation);
+        let crate_types = invocation.crate_types_or_default();
+
+        let mut artifacts = Vec::new();
+        for kind in outputs.kinds() {
+            match kind {
+                EmitKind::Link => {
+                    for crate_type in crate_types.iter().copied() {
+                        check_crate_type(crate_type)?;
+
+                        artifacts.push(Artifact {
+                            emit: kind,
+                            crate_type: Some(crate_type),
+                            path: outputs.link_path(target, crate_type),
+                        });
+                    }
+                }
+                // Dependency information is written by the driver itself, after
+                // compiling the crate
+                EmitKind::DepInfo => {}
+                EmitKind::LlvmBc => {
+                    return Err(Error::InvalidArg(String::from(
+                        "gccrs cannot emit LLVM bitcode",
+                    )))
+                }
+                kind => artifacts.push(Artifact {
+                    emit: kind,
+                    crate_type: None,
+                    path: outputs.path(kind),
+                }),
+            }
+        }
 
-        let args_set: Result<Vec<Args>> = matches
-            .opt_strs("crate-type")
+        // Only crates linked into a final artifact need the link information of every
+        // upstream library. Rust libraries must not read it: When pipelining, `cargo`
+        // starts compiling them before their dependencies are done writing it
+        let final_link = artifacts.iter().any(|artifact| {
+            artifact
+                .crate_type
+                .is_some_and(|ty| !matches!(ty, CrateType::Lib | CrateType::Rlib))
+        });
+        let archives = match final_link {
+            true => LinkInfo::link_order(&dependencies)?,
+            false => Vec::new(),
+        };
+        LinkInfo::check_panic_strategy(&archives, invocation.codegen.panic_strategy())?;
+
+        // Upstream archives come first, followed by the native libraries they require
+        let mut upstream_link_args = archives
             .iter()
-            .map(|type_str| CrateType::from(type_str.as_str()))
-            .map(|crate_type| format_output_filename(matches, crate_type))
-            .map(|result_tuple| {
-                result_tuple.map(|(output_file, crate_type)| {
-                    Args::new(&matches.free, crate_type, output_file)
-                })
-            })
-            .collect();
+            .map(|archive| Ok(archive.clone().into_os_string().into_string()?))
+            .collect::<Result<Vec<String>>>()?;
+        upstream_link_args.append(&mut LinkInfo::native_args(&archives)?);
+
+        let args = Args::new(
+            invocation,
+            target,
+            &outputs,
+            &artifacts,
+            &dependencies,
+            &upstream_link_args,
+        )?;
 
         Ok(ArgsCollection {
-            args_set: args_set?,
+            args,
+            artifacts,
+            outputs,
         })
     }
-}
 
-impl ArgsCollection {
-    /// Access the collection's inner data
-    pub fn data(&self) -> &Vec<Args> {
-        &self.args_set
+    /// Get the arguments used to compile the crate
+    pub fn args(&self) -> &Args {
+        &self.args
     }
-}
 
-/// Crate types supported by `gccrs`
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub enum CrateType {
-    /// Binary application
-    Bin,
-    /// Dynamic library/Shared object
-    DyLib,
-    /// Statically linked library
-    StaticLib,
-    /// Remaining options, handled by `rustc` but not `gccrs`
-    Unknown,
-}
+    /// Get the artifacts to produce once the crate is compiled, in order
+    pub fn artifacts(&self) -> &[Artifact] {
+        &self.artifacts
+    }
 
-/// Get the corresponding [`CrateType`] from a given option to the `--crate-type`
-/// option
-impl<'a> From<&'a str> for CrateType {
-    fn from(s: &'a str) -> CrateType {
-        match s {
-            "bin" => CrateType::Bin,
-            "dylib" => CrateType::DyLib,
-            "staticlib" => CrateType::StaticLib,
-            _ => CrateType::Unknown,
-        }
+    /// Get the paths of the outputs requested by the `rustc` invocation
+    pub fn outputs(&self) -> &OutputFilenames {
+        &self.outputs
     }
 }
 
-fn format_output_filename(
-    matches: &Matches,
-    crate_type: CrateType,
-) -> Result<(PathBuf, CrateType)> {
-    // Return an [`Error::InvalidArg`] error if `--crate-name` or `out-dir` weren't
-    // given as arguments at this point of the translation
-    let crate_name = matches
-        .opt_str("crate-name")
-        .ok_or_else(|| Error::InvalidArg(String::from("no `--crate-name` provided")))?;
-    let out_dir = matches
-        .opt_str("out-dir")
-        .ok_or_else(|| Error::InvalidArg(String::from("no `--out-dir` provided")))?;
-    let c_options = matches.opt_strs("C");
-
-    let mut output_file = PathBuf::from(&out_dir);
-
-    // FIXME: Horrendous. We need to create a separate "C options" parser since we'll
-    // probably use more than just `extra-filename`. Issue #6 on Rust-GCC/cargo-gccrs
-    let extra_filename = c_options
-        .iter()
-        .filter_map(|c_opt| {
-            let mut split = c_opt.split('=');
-
-            if let Some("extra-filename") = split.next() {
-                split.next()
-            } else {
-                None
-            }
-        })
-        .collect::<Vec<&str>>()
-        .pop()
-        .unwrap_or("");
-
+fn check_crate_type(crate_type: CrateType) -> Result {
     match crate_type {
-        CrateType::Bin => output_file.push(&format!("{crate_name}{extra_filename}")),
-        CrateType::DyLib => output_file.push(&format!("lib{crate_name}{extra_filename}.so")),
-        CrateType::StaticLib => output_file.push(&format!("lib{crate_name}{extra_filename}.a")),
-        _ => unreachable!(
-            "gccrs cannot handle other crate types than bin, dylib or staticlib at the moment"
-        ),
+        CrateType::Bin
+        | CrateType::Lib
+        | CrateType::Rlib
+        | CrateType::DyLib
+        | CrateType::CDyLib
+        | CrateType::StaticLib => Ok(()),
+        _ => Err(Error::InvalidArg(format!(
+            "gccrs cannot handle `--crate-type {crate_type}` at the moment"
+        ))),
     }
+}
 
-    Ok((output_file, crate_type))
+/// An output produced from the crate's object file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Artifact {
+    pub emit: EmitKind,
+    /// Crate type of linked artifacts
+    pub crate_type: Option<CrateType>,
+    pub path: PathBuf,
 }
 
 /// Structure used to represent arguments passed to `gccrs`. Convert them from `rustc`
-/// arguments using [`Args::from_rustc_arg`]
+/// arguments using [`ArgsCollection::new`]
 pub struct Args {
     source_files: Vec<String>,
-    crate_type: CrateType,
-    output_file: PathBuf,
+    crate_name: String,
+    /// Relocation model of the object file shared by every artifact
+    object_relocation: RelocModel,
+    /// Relocation model requested for executables
+    relocation_model: RelocModel,
+    /// Link executables statically, using `-C target-feature=+crt-static`
+    crt_static: bool,
+    object_file: PathBuf,
+    /// Make rules listing the files the crate is compiled from, written by `gccrs` when
+    /// dependency information is requested
+    dep_file: Option<PathBuf>,
+    /// Path of the crate metadata exported by `gccrs`, if needed
+    metadata_export: Option<PathBuf>,
+    /// Export the crate metadata in a front-end only pass before generating code, so
+    /// that `cargo` can start compiling dependent crates early
+    pipelined: bool,
+    /// Generate code for the crate. `cargo check` only asks for its metadata, which the
+    /// front-end only pass produces on its own
+    codegen: bool,
+    /// Optimization, debug information and runtime checks requested with `-C`
+    codegen_options: Vec<String>,
+    /// Edition and configuration options of the crate
+    language_args: Vec<String>,
+    /// Target CPU and features requested with `-C`
+    machine_args: Vec<String>,
+    /// Warning options matching the lint levels and `--cap-lints`
+    lint_args: Vec<String>,
+    /// Link-time optimization mode, given when compiling and linking the crate
+    lto: Vec<String>,
+    /// Optimization level, which GCC also needs when optimizing at link time
+    opt_level: Option<String>,
+    /// Sanitizers enabled when compiling and linking the crate
+    sanitize: Option<String>,
+    /// Panic strategy of the crate, stored next to Rust libraries
+    panic: PanicStrategy,
+    /// Where the debug information of the crate goes, if there is any
+    split_debuginfo: SplitDebuginfo,
+    strip: Strip,
+    /// Profile-guided optimization options, given when compiling and linking the crate
+    profile: Vec<String>,
+    /// Name of the crate's profile data, which must not depend on the build profile for
+    /// the data to be found again
+    profile_name: String,
+    /// Instrument the crate for `gcov`, using `-C instrument-coverage`
+    coverage: bool,
+    /// Base name of the coverage notes and data of the crate, in the output directory
+    coverage_name: PathBuf,
+    /// GCC dumps requested to emit compiler internal representations
+    dumps: Vec<String>,
+    search_paths: Vec<SearchPath>,
+    dependencies: Vec<Dependency>,
+    native_libs: Vec<NativeLib>,
+    /// Arguments needed to link the crate's dependencies, including transitive ones
+    upstream_link_args: Vec<String>,
+    /// Compiler driver given with `-C linker`, which replaces `gccrs` for the final link
+    linker: Option<PathBuf>,
+    /// Options making `gccrs` use the linker given with `-C linker`, if it is not a
+    /// compiler driver
+    linker_selection: Vec<String>,
+    /// Arguments given with `-C link-arg` and `-C link-args`
+    user_link_args: Vec<String>,
+    /// Libraries linked explicitly when `-C default-linker-libraries=no` is given
+    default_libs: Option<Vec<String>>,
 }
 
 impl Args {
-    fn new(source_files: &[String], crate_type: CrateType, output_file: PathBuf) -> Args {
-        Args {
-            source_files: Vec::from(source_files),
+    fn new(
+        invocation: &RustcInvocation,
+        target: &Target,
+        outputs: &OutputFilenames,
+        artifacts: &[Artifact],
+        dependencies: &[Dependency],
+        upstream_link_args: &[String],
+    ) -> Result<Args> {
+        let (linker, linker_selection) = Args::linker(invocation.codegen.linker.as_deref());
+
+        // Linkers invoked through `-fuse-ld` expect their own options, which must go
+        // through the compiler driver
+        let user_link_args = invocation
+            .codegen
+            .link_args
+            .iter()
+            .map(|arg| match linker_selection.is_empty() {
+                true => arg.clone(),
+                false => format!("-Wl,{arg}"),
+            })
+            .collect();
+
+        // `gcc` always links with the startup objects and runtime libraries of its own
+        // installation, which is what `-C link-self-contained=yes` asks for. They cannot
+        // be replaced by the ones of the system
+        if invocation.codegen.link_self_contained == Some(false) {
+            return Err(Error::InvalidArg(String::from(
+                "gccrs cannot handle `-C link-self-contained=no` at the moment",
+            )));
+        }
+
+        let default_libs = match invocation.codegen.default_linker_libraries {
+            Some(false) => Some(
+                target
+                    .native_static_libs()
+                    .iter()
+                    .map(|lib| format!("-l{lib}"))
+                    .collect(),
+            ),
+            _ => None,
+        };
+
+        let relocation_model = match invocation.codegen.relocation_model {
+            None | Some(RelocModel::Default) => RelocModel::Pic,
+            Some(
+                model @ (RelocModel::Pic
+                | RelocModel::Pie
+                | RelocModel::Static
+                | RelocModel::DynamicNoPic),
+            ) => model,
+            Some(model) => {
+                return Err(Error::InvalidArg(format!(
+                    "gccrs cannot handle `-C relocation-model={model}` at the moment"
+                )))
+            }
+        };
+        let object_relocation = Args::object_relocation(relocation_model, artifacts);
+
+        // Rust libraries need the exported metadata next to them. Otherwise, export it
+        // next to the requested metadata artifact
+        let rlib = artifacts.iter().find(|artifact| {
+            matches!(
+                artifact.crate_type,
+                Some(CrateType::Lib) | Some(CrateType::Rlib)
+            )
+        });
+        let metadata = artifacts
+            .iter()
+            .find(|artifact| artifact.emit == EmitKind::Metadata);
+        let metadata_export = rlib
+            .or(metadata)
+            .map(|artifact| dependencies::metadata_file(&artifact.path));
+        let pipelined = metadata.is_some();
+        let codegen = artifacts
+            .iter()
+            .any(|artifact| artifact.emit != EmitKind::Metadata);
+
+        let dumps = artifacts
+            .iter()
+            .filter_map(|artifact| match artifact.emit {
+                // The closest thing to LLVM IR is the optimized GIMPLE of the crate
+                EmitKind::LlvmIr => {
+                    Some(format!("-fdump-tree-optimized={}", artifact.path.display()))
+                }
+                // `gccrs` dumps its HIR in the current directory, under a fixed name
+                EmitKind::Mir => Some(String::from("-frust-dump-hir-pretty")),
+                _ => None,
+            })
+            .collect();
+
+        Ok(Args {
+            source_files: Vec::from(invocation.input.as_slice()),
+            crate_name: invocation.output_crate_name(),
+            object_relocation,
+            relocation_model,
+            crt_static: invocation.codegen.target_feature("crt-static") == Some(true),
+            object_file: outputs.temp_path("tmp_object.o"),
+            dep_file: match outputs.contains(EmitKind::DepInfo) {
+                true => Some(outputs.temp_path("gccrs.d")),
+                false => None,
+            },
+            metadata_export,
+            pipelined,
+            codegen,
+            codegen_options: Args::codegen_options(&invocation.codegen),
+            language_args: Args::language_args(invocation)?,
+            machine_args: target_features::machine_args(target, &invocation.codegen)?,
+            lint_args: lints::lint_args(&invocation.lints, invocation.cap_lints),
+            lto: Args::lto(
+                &invocation.codegen,
+                &artifacts
+                    .iter()
+                    .filter_map(|artifact| artifact.crate_type)
+                    .collect::<Vec<CrateType>>(),
+            ),
+            opt_level: Args::opt_level(&invocation.codegen),
+            sanitize: sanitizers::sanitize_option(target, &invocation.codegen.sanitizers)?,
+            panic: invocation.codegen.panic_strategy(),
+            split_debuginfo: Args::split_debuginfo(&invocation.codegen, target),
+            strip: invocation.codegen.strip.unwrap_or(Strip::None),
+            profile: Args::profile(&invocation.codegen)?,
+            coverage: invocation.codegen.instrument_coverage == Some(true),
+            coverage_name: outputs.base_path(),
+            profile_name: Args::profile_name(invocation),
+            dumps,
+            search_paths: invocation.search_paths.clone(),
+            dependencies: Vec::from(dependencies),
+            native_libs: invocation.native_libs.clone(),
+            upstream_link_args: Vec::from(upstream_link_args),
+            linker,
+            linker_selection,
+            user_link_args,
+            default_libs,
+        })
+    }
+
+    /// Interpret the program given with `-C linker`. Like `rustc`, infer its flavor from
+    /// its name: Actual linkers are selected using `-fuse-ld`, while any other program
+    /// is considered a compiler driver accepting the same options as `gccrs`
+    fn linker(linker: Option<&Path>) -> (Option<PathBuf>, Vec<String>) {
+        let linker = match linker {
+            Some(linker) => linker,
+            None => return (None, vec![]),
+        };
+
+        let name = linker
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.trim_end_matches(".exe"))
+            .unwrap_or_default();
+        let flavor = match name {
+            name if name.ends_with("mold") => "mold",
+            name if name.ends_with("lld") => "lld",
+            name if name.ends_with("gold") => "gold",
+            name if name == "ld" || name.ends_with("-ld") || name.ends_with("ld.bfd") => "bfd",
+            _ => return (Some(linker.to_path_buf()), vec![]),
+        };
+
+        // Let `gcc` find the linker next to the given path, if any
+        let mut selection = vec![format!("-fuse-ld={flavor}")];
+        if let Some(dir) = linker.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            selection.push(format!("-B{}", dir.display()));
+        }
+
+        (None, selection)
+    }
+
+    /// Check if the object file contains GCC's intermediate representation, to be
+    /// optimized at link time
+    fn lto_objects(&self) -> bool {
+        self.lto.iter().any(|arg| arg.starts_with("-flto"))
+    }
+
+    /// Get the archiver for the crate's object file. Archives of LTO objects need
+    /// `gcc-ar`, which indexes their symbols using GCC's linker plugin
+    pub fn archiver(&self) -> &'static str {
+        match self.lto_objects() {
+            true => "gcc-ar",
+            false => "ar",
+        }
+    }
+
+    /// Debug information can only be split out of ELF binaries for now, and only if
+    /// there is any
+    fn split_debuginfo(codegen: &CodegenOptions, target: &Target) -> SplitDebuginfo {
+        let debuginfo = !matches!(codegen.debuginfo, None | Some(DebugInfo::None));
+
+        match codegen.split_debuginfo {
+            Some(split_debuginfo) if debuginfo && target.is_elf() => split_debuginfo,
+            _ => SplitDebuginfo::Off,
+        }
+    }
+
+    /// Get the companion file receiving the debug information of a linked artifact,
+    /// when packing it
+    pub fn debuginfo_file(&self, artifact: &Artifact) -> Option<PathBuf> {
+        let mut file_name = artifact.path.file_name()?.to_owned();
+        file_name.push(".debug");
+
+        match self.split_debuginfo {
+            SplitDebuginfo::Packed => Some(artifact.path.with_file_name(file_name)),
+            _ => None,
+        }
+    }
+
+    /// Get the `objcopy` option stripping a linked artifact, if it was not already
+    /// stripped by the linker
+    pub fn strip_option(&self) -> Option<&'static str> {
+        match (self.strip, self.split_debuginfo) {
+            (Strip::Debuginfo, _) => Some("--strip-debug"),
+            (Strip::Symbols, SplitDebuginfo::Packed) => Some("--strip-all"),
+            _ => None,
+        }
+    }
+
+    /// Get the panic strategy of the crate
+    pub fn panic_strategy(&self) -> PanicStrategy {
+        self.panic
+    }
+
+    /// Get the compiler driver to use for the final link instead of `gccrs`, if any
+    pub fn linker_program(&self) -> Option<&Path> {
+        self.linker.as_deref()
+    }
+
+    /// Every artifact is produced from the same object file, which must be position
+    /// independent as soon as it ends up in a shared object or in a static library
+    /// linked by others. Otherwise, the object follows the requested relocation model,
+    /// and executables default to position independent ones
+    fn object_relocation(relocation_model: RelocModel, artifacts: &[Artifact]) -> RelocModel {
+        let crate_types = || artifacts.iter().filter_map(|artifact| artifact.crate_type);
+
+        let shared = crate_types().any(|crate_type| {
+            matches!(
+                crate_type,
+                CrateType::DyLib | CrateType::CDyLib | CrateType::StaticLib | CrateType::ProcMacro
+            )
+        });
+        let executable_only = crate_types().all(|crate_type| crate_type == CrateType::Bin);
+
+        match relocation_model {
+            _ if shared => RelocModel::Pic,
+            RelocModel::Pic if executable_only => RelocModel::Pie,
+            model => model,
+        }
+    }
+
+    fn opt_level(codegen: &CodegenOptions) -> Option<String> {
+        codegen.opt_level.map(|opt_level| {
+            String::from(match opt_level {
+                OptLevel::Zero => "-O0",
+                OptLevel::One => "-O1",
+                OptLevel::Two => "-O2",
+                OptLevel::Three => "-O3",
+                OptLevel::Size => "-Os",
+                OptLevel::SizeMin => "-Oz",
+            })
+        })
+    }
+
+    /// Translate `-C lto` into GCC's LTO modes. Fat LTO optimizes the whole program as
+    /// a single partition, while thin LTO lets GCC partition it. Both use `make`'s
+    /// jobserver when `cargo` provides one, and every CPU otherwise.
+    ///
+    /// Like `rustc`, Rust libraries contain what the final link needs to optimize them
+    /// across crates, unless given `-C embed-bitcode=no`: `cargo` only passes it when the
+    /// profile disables LTO. Static libraries and C dynamic libraries are used by links
+    /// which may not optimize at link time. All of these get fat LTO objects, which can
+    /// be linked with or without LTO
+    fn lto(codegen: &CodegenOptions, crate_types: &[CrateType]) -> Vec<String> {
+        let rust_library = crate_types
+            .iter()
+            .any(|crate_type| matches!(crate_type, CrateType::Lib | CrateType::Rlib));
+        let foreign_library = crate_types
+            .iter()
+            .any(|crate_type| matches!(crate_type, CrateType::StaticLib | CrateType::CDyLib));
+        let cross_crate =
+            codegen.embed_bitcode != Some(false) || codegen.linker_plugin_lto_enabled();
+
+        let mut args = match codegen.lto {
+            Some(Lto::Fat) => vec!["-flto=auto", "-flto-partition=one"],
+            Some(Lto::Thin) => vec!["-flto=auto"],
+            Some(Lto::Off) => vec!["-fno-lto"],
+            None if rust_library && cross_crate => vec!["-flto=auto"],
+            None => vec![],
+        };
+        if args.contains(&"-flto=auto") && (rust_library || foreign_library) {
+            args.push("-ffat-lto-objects");
+        }
+
+        args.into_iter().map(String::from).collect()
+    }
+
+    /// Translate `-C profile-generate` and `-C profile-use`. `gcc` writes one `.gcda`
+    /// file per object in the profile directory, and reads them back from the same
+    /// directory: A profile file given to `-C profile-use` stands for its directory.
+    /// Crates which were not exercised have no profile data, which is not an error
+    fn profile(codegen: &CodegenOptions) -> Result<Vec<String>> {
+        if let Some(dir) = &codegen.profile_generate {
+            return Ok(vec![format!(
+                "-fprofile-generate={}",
+                dir.clone().into_os_string().into_string()?
+            )]);
+        }
+
+        let profile = match &codegen.profile_use {
+            Some(profile) => profile,
+            None => return Ok(vec![]),
+        };
+        let dir = match profile.is_file() {
+            true => profile.parent().map(Path::to_path_buf).unwrap_or_default(),
+            false => profile.clone(),
+        };
+
+        Ok(vec![
+            format!("-fprofile-use={}", dir.into_os_string().into_string()?),
+            String::from("-fprofile-correction"),
+            String::from("-Wno-missing-profile"),
+        ])
+    }
+
+    /// Name the profile data of the crate after its name and crate types, as well as the
+    /// `-C metadata` hash telling apart crates with the same name in a dependency graph.
+    /// `cargo` keeps this hash the same when only the rustflags change, as they do
+    /// between the instrumented and the optimized builds
+    fn profile_name(invocation: &RustcInvocation) -> String {
+        let crate_types = invocation.crate_types_or_default();
+
+        crate_types
+            .iter()
+            .map(CrateType::to_string)
+            .chain(invocation.codegen.metadata.iter().cloned())
+            .fold(invocation.output_crate_name(), |name, part| {
+                format!("{name}-{part}")
+            })
+    }
+
+    /// Translate the edition and the configuration options in effect, including the
+    /// ones implied by codegen options such as `panic`
+    fn language_args(invocation: &RustcInvocation) -> Result<Vec<String>> {
+        let mut args = Vec::new();
+
+        match invocation.edition {
+            None => {}
+            Some(edition @ (Edition::E2015 | Edition::E2018 | Edition::E2021)) => {
+                args.push(format!("-frust-edition={edition}"))
+            }
+            Some(edition) => {
+                return Err(Error::InvalidArg(format!(
+                    "gccrs cannot handle `--edition {edition}` at the moment"
+                )))
+            }
+        }
+
+        // `debug_assertions` is set along with the other codegen options when they imply
+        // it, and must only be given here when the user sets it explicitly
+        let implied = invocation.codegen.debug_assertions_enabled();
+        args.extend(
+            invocation
+                .all_cfgs()
+                .iter()
+                .filter(|cfg| !(implied && cfg.name == "debug_assertions" && cfg.value.is_none()))
+                .map(|cfg| format!("-frust-cfg={cfg}")),
+        );
+
+        Ok(args)
+    }
+
+    /// Translate the codegen options `cargo` derives from the build profile into their
+    /// `gcc` counterparts
+    fn codegen_options(codegen: &CodegenOptions) -> Vec<String> {
+        let mut args = Vec::new();
+
+        args.extend(Args::opt_level(codegen));
+
+        // `gcc` does not differentiate between line tables and limited debug information
+        if let Some(debuginfo) = codegen.debuginfo {
+            args.push(String::from(match debuginfo {
+                DebugInfo::None => "-g0",
+                DebugInfo::LineDirectivesOnly | DebugInfo::LineTablesOnly | DebugInfo::Limited => {
+                    "-g1"
+                }
+                DebugInfo::Full => "-g2",
+            }));
+        }
+
+        // Debug assertions are only a configuration option, which `rustc` sets in the
+        // same situations
+        if codegen.debug_assertions_enabled() {
+            args.push(String::from("-frust-cfg=debug_assertions"));
+        }
+
+        // `gccrs` has no switch controlling overflow checks, which are thus ignored
+
+        // Crates which abort on panic never unwind through their frames. Unwind tables
+        // are still kept unless disabled, for backtraces and debuggers
+        match codegen.panic_strategy() {
+            PanicStrategy::Unwind => args.append(&mut vec![
+                String::from("-frust-panic=unwind"),
+                String::from("-fexceptions"),
+            ]),
+            PanicStrategy::Abort => args.append(&mut vec![
+                String::from("-frust-panic=abort"),
+                String::from("-fno-exceptions"),
+            ]),
+        }
+        match codegen.force_unwind_tables {
+            Some(true) => args.push(String::from("-fasynchronous-unwind-tables")),
+            Some(false) => args.push(String::from("-fno-asynchronous-unwind-tables")),
+            None => {}
+        }
+
+        args
+    }
+
+    /// Get the path of the make rules written by `gccrs`, if dependency information was
+    /// requested
+    pub fn dep_file(&self) -> Option<&Path> {
+        self.dep_file.as_deref()
+    }
+
+    /// Get the path of the object file the crate is compiled to
+    pub fn object_file(&self) -> &Path {
+        &self.object_file
+    }
+
+    /// Get the path of the crate metadata exported by `gccrs` when compiling, if any
+    pub fn metadata_export(&self) -> Option<&Path> {
+        self.metadata_export.as_deref()
+    }
+
+    /// Check if the crate metadata is produced before the rest of the artifacts
+    pub fn pipelined(&self) -> bool {
+        self.pipelined
+    }
+
+    /// Check if the crate needs to be compiled to an object file
+    pub fn codegen(&self) -> bool {
+        self.codegen
+    }
+
+    /// Get the external crates used by the crate
+    pub fn dependencies(&self) -> &[Dependency] {
+        &self.dependencies
+    }
+
+    /// Translate `-L` search paths and `--extern` crates into `gccrs` options. `gccrs`
+    /// looks for crates in the same search paths as the linker
+    fn dependency_args(&self) -> Result<Vec<String>> {
+        let mut args = Vec::new();
+
+        for search_path in self.search_paths.iter() {
+            let flag = match search_path.kind {
+                SearchPathKind::Framework => "-F",
+                _ => "-L",
+            };
+
+            args.push(String::from(flag));
+            args.push(search_path.path.clone().into_os_string().into_string()?);
+        }
+
+        for dependency in self.dependencies.iter() {
+            let metadata = dependency.metadata.clone().into_os_string().into_string()?;
+
+            args.push(format!("-frust-extern={}={metadata}", dependency.name));
+        }
+
+        Ok(args)
+    }
+
+    /// Native static libraries are bundled inside of the archives produced by `gccrs`,
+    /// unless the `-bundle` modifier is used
+    fn is_bundled(lib: &NativeLib, crate_type: CrateType) -> bool {
+        let produces_archive = matches!(
             crate_type,
-            output_file,
+            CrateType::Lib | CrateType::Rlib | CrateType::StaticLib
+        );
+
+        produces_archive
+            && lib.kind == NativeLibKind::Static
+            && lib.modifier(LinkModifier::Bundle).unwrap_or(true)
+    }
+
+    /// Translate a native library into GCC link options. Modifiers are scoped to the
+    /// library using the linker's state stack
+    fn native_lib_args(lib: &NativeLib) -> Result<Vec<String>> {
+        let lib_flag = match lib.modifier(LinkModifier::Verbatim) {
+            Some(true) => format!("-l:{}", lib.name),
+            _ => format!("-l{}", lib.name),
+        };
+
+        let mut state = Vec::new();
+        if lib.kind == NativeLibKind::Static {
+            state.push("-Bstatic");
+        }
+        match lib.modifier(LinkModifier::WholeArchive) {
+            Some(true) => state.push("--whole-archive"),
+            Some(false) => state.push("--no-whole-archive"),
+            None => {}
+        }
+        match lib.modifier(LinkModifier::AsNeeded) {
+            Some(true) => state.push("--as-needed"),
+            Some(false) => state.push("--no-as-needed"),
+            None => {}
+        }
+
+        match lib.kind {
+            NativeLibKind::Framework => Ok(vec![String::from("-framework"), lib.name.clone()]),
+            NativeLibKind::LinkArg => Ok(vec![lib.name.clone()]),
+            NativeLibKind::RawDylib => Err(Error::InvalidArg(format!(
+                "gccrs cannot link `raw-dylib` library `{}`",
+                lib.name
+            ))),
+            _ if state.is_empty() => Ok(vec![lib_flag]),
+            _ => Ok(vec![
+                format!("-Wl,--push-state,{}", state.join(",")),
+                lib_flag,
+                String::from("-Wl,--pop-state"),
+            ]),
         }
     }
 
-    /// Add `.tmp.o` to the expected output filename.
-    pub fn object_file_name(&self) -> PathBuf {
-        let mut tmp_object_path = self.output_file.clone();
-        tmp_object_path.set_extension("tmp_object.o");
+    /// Get the link options of every native library which is not bundled inside of the
+    /// archive produced for a crate type, if any
+    pub fn native_args(&self, crate_type: CrateType) -> Result<Vec<String>> {
+        let mut args = Vec::new();
+
+        for lib in self
+            .native_libs
+            .iter()
+            .filter(|lib| !Args::is_bundled(lib, crate_type))
+        {
+            args.append(&mut Args::native_lib_args(lib)?);
+        }
 
-        tmp_object_path
+        Ok(args)
     }
 
-    /// Get a reference to the set of arguments' output file path
-    pub fn output_file(&self) -> &Path {
-        &self.output_file
+    /// Find the native static libraries to bundle inside of the archive produced for a
+    /// crate type in the native search paths
+    pub fn bundled_libs(&self, crate_type: CrateType) -> Result<Vec<PathBuf>> {
+        let search_dirs: Vec<&Path> = self
+            .search_paths
+            .iter()
+            .filter(|search_path| {
+                matches!(
+                    search_path.kind,
+                    SearchPathKind::Native | SearchPathKind::All
+                )
+            })
+            .map(|search_path| search_path.path.as_path())
+            .collect();
+
+        self.native_libs
+            .iter()
+            .filter(|lib| Args::is_bundled(lib, crate_type))
+            .map(|lib| {
+                let file_name = match lib.modifier(LinkModifier::Verbatim) {
+                    Some(true) => lib.name.clone(),
+                    _ => format!("lib{}.a", lib.name),
+                };
+
+                search_dirs
+                    .iter()
+                    .map(|dir| dir.join(&file_name))
+                    .find(|path| path.is_file())
+                    .ok_or_else(|| {
+                        Error::InvalidArg(format!(
+                            "could not find native static library `{}`, perhaps an -L flag is missing?",
+                            lib.name
+                        ))
+                    })
+            })
+            .collect()
     }
 
-    /// Get a reference to the set of arguments' type of binary produced
-    pub fn crate_type(&self) -> CrateType {
-        self.crate_type
+    /// Arguments given to the linker after the crate's own code: Its native libraries,
+    /// followed by its dependencies
+    fn link_args(&self, crate_type: CrateType) -> Result<Vec<String>> {
+        let mut args = self.native_args(crate_type)?;
+        args.extend(self.upstream_link_args.iter().cloned());
+
+        Ok(args)
     }
 
-    /// Create arguments usable when spawning a process from an instance of [`Args`]
-    pub fn as_args(&self) -> Result<Vec<String>> {
-        // `rustc` generates position independant code
-        let mut args = vec![String::from("-fPIE"), String::from("-pie")];
+    /// Arguments common to every `gccrs` invocation compiling the crate
+    fn frontend_args(&self) -> Result<Vec<String>> {
+        let mut args = vec![String::from(match self.object_relocation {
+            RelocModel::Pic => "-fPIC",
+            RelocModel::Pie => "-fPIE",
+            _ => "-fno-pie",
+        })];
+        args.append(&mut self.language_args.clone());
+        args.append(&mut self.codegen_options.clone());
+        args.append(&mut self.machine_args.clone());
+        args.append(&mut self.lint_args.clone());
+        args.append(&mut self.lto.clone());
+        // Sanitizers report stack traces, which need frame pointers
+        if let Some(sanitize) = &self.sanitize {
+            args.push(sanitize.clone());
+            args.push(String::from("-fno-omit-frame-pointer"));
+        }
+        args.append(&mut self.profile.clone());
+        if self.coverage {
+            args.push(String::from("--coverage"));
+        }
+
+        // Profiling data is named after the dump base name, as are the `.gcno` notes
+        // `gcov` needs next to the coverage data
+        let dumpbase = match (self.coverage, self.profile.is_empty()) {
+            (true, _) => Some(self.coverage_name.clone().into_os_string().into_string()?),
+            (false, false) => Some(self.profile_name.clone()),
+            (false, true) => None,
+        };
+        if let Some(dumpbase) = dumpbase {
+            args.push(String::from("-dumpbase"));
+            args.push(dumpbase);
+        }
+        if let Some(dep_file) = &self.dep_file {
+            args.push(String::from("-MD"));
+            args.push(String::from("-MF"));
+            args.push(dep_file.clone().into_os_string().into_string()?);
+        }
         args.append(&mut self.source_files.clone());
+        args.push(format!("-frust-crate={}", self.crate_name));
+        args.append(&mut self.dependency_args()?);
 
         if let Some(mut user_compiler_args) = EnvArgs::Gcc.as_args() {
             args.append(&mut user_compiler_args);
         }
 
-        let output_file = self.output_file().as_os_str().to_owned().into_string()?;
+        Ok(args)
+    }
 
-        match self.crate_type {
-            CrateType::Bin => args.append(&mut vec![String::from("-o"), output_file]),
-            CrateType::DyLib => args.append(&mut vec![
-                String::from("-shared"),
-                String::from("-o"),
-                output_file,
-            ]),
-            CrateType::StaticLib => args.append(&mut vec![
-                String::from("-c"),
-                String::from("-o"),
-                self.object_file_name().into_os_string().into_string()?,
-            ]),
-            CrateType::Unknown => {}
+    fn metadata_export_arg(&self) -> Result<Option<String>> {
+        match &self.metadata_export {
+            Some(metadata_export) => Ok(Some(format!(
+                "-frust-metadata-output={}",
+                metadata_export.clone().into_os_string().into_string()?
+            ))),
+            None => Ok(None),
+        }
+    }
+
+    /// Create the arguments exporting the crate metadata without generating code.
+    /// `gccrs` stops once the crate is lowered to GCC's intermediate representation,
+    /// after its privacy, unsafety and lint passes have run
+    pub fn metadata_args(&self) -> Result<Vec<String>> {
+        let mut args = self.frontend_args()?;
+        args.push(String::from("-fsyntax-only"));
+        args.push(String::from("-frust-compile-until=compilation"));
+        args.extend(self.metadata_export_arg()?);
+
+        Ok(args)
+    }
+
+    /// Create the arguments compiling the crate to its object file
+    pub fn compile_args(&self) -> Result<Vec<String>> {
+        let mut args = self.frontend_args()?;
+        args.append(&mut vec![
+            String::from("-c"),
+            String::from("-o"),
+            self.object_file.clone().into_os_string().into_string()?,
+        ]);
+
+        if self.split_debuginfo == SplitDebuginfo::Unpacked {
+            args.push(String::from("-gsplit-dwarf"));
+        }
+
+        // Rust libraries are archives of object files, like static libraries. On top of
+        // that, `gccrs` exports the crate's metadata so that dependent crates can later
+        // use it. When pipelining, the metadata was already exported, and must not be
+        // overwritten while dependent crates use it
+        if !self.pipelined {
+            args.extend(self.metadata_export_arg()?);
+        }
+
+        args.append(&mut self.dumps.clone());
+
+        Ok(args)
+    }
+
+    /// Create the arguments compiling the crate to assembly
+    pub fn asm_args(&self, artifact: &Artifact) -> Result<Vec<String>> {
+        let mut args = self.frontend_args()?;
+        args.append(&mut vec![
+            String::from("-S"),
+            String::from("-o"),
+            artifact.path.clone().into_os_string().into_string()?,
+        ]);
+
+        Ok(args)
+    }
+
+    /// Create the arguments linking the crate's object file into an executable or a
+    /// shared library
+    pub fn link_args_for(&self, artifact: &Artifact, crate_type: CrateType) -> Result<Vec<String>> {
+        let executable = match (self.relocation_model, self.crt_static) {
+            (RelocModel::Static | RelocModel::DynamicNoPic, false) => "-no-pie",
+            (RelocModel::Static | RelocModel::DynamicNoPic, true) => "-static",
+            (_, false) => "-pie",
+            (_, true) => "-static-pie",
+        };
+        let mut args = match crate_type {
+            CrateType::DyLib | CrateType::CDyLib => vec![String::from("-shared")],
+            _ => vec![String::from(executable)],
+        };
+        args.append(&mut self.linker_selection.clone());
+        if self.lto_objects() {
+            args.append(&mut self.lto.clone());
+            args.extend(self.opt_level.clone());
+        }
+        // Instrumented objects need `gcc`'s profiling and sanitizer runtimes
+        args.append(&mut self.profile.clone());
+        if self.coverage {
+            args.push(String::from("--coverage"));
+        }
+        args.extend(self.sanitize.clone());
+
+        // Symbols are stripped after packing the debug information otherwise
+        if self.strip == Strip::Symbols && self.split_debuginfo != SplitDebuginfo::Packed {
+            args.push(String::from("-s"));
+        }
+        args.push(self.object_file.clone().into_os_string().into_string()?);
+
+        if let Some(mut user_compiler_args) = EnvArgs::Gcc.as_args() {
+            args.append(&mut user_compiler_args);
+        }
+
+        args.push(String::from("-o"));
+        args.push(artifact.path.clone().into_os_string().into_string()?);
+        args.append(&mut self.link_args(crate_type)?);
+
+        // Like `rustc`, give the user's link arguments after the libraries
+        args.append(&mut self.user_link_args.clone());
+
+        if let Some(default_libs) = &self.default_libs {
+            args.push(String::from("-nodefaultlibs"));
+            args.append(&mut default_libs.clone());
         }
 
         Ok(args)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    /// Plan the compilation of crate `foo` from an input file, with its outputs in `out`
+    fn parse(input: &str, args: &[&str]) -> Result<ArgsCollection> {
+        let args: Vec<String> = [input, "--crate-name", "foo", "--out-dir", "out"]
+            .iter()
+            .chain(args)
+            .map(|arg| arg.to_string())
+            .collect();
+        let invocation = RustcInvocation::try_from(args.as_slice())?;
+
+        ArgsCollection::new(&invocation, &Target::parse("x86_64-linux-gnu")?)
+    }
+
+    fn artifact_paths(collection: &ArgsCollection) -> Vec<&Path> {
+        collection
+            .artifacts()
+            .iter()
+            .map(|artifact| artifact.path.as_path())
+            .collect()
+    }
+
+    fn native_args(lib: &str) -> Vec<String> {
+        Args::native_lib_args(&NativeLib::try_from(lib).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn native_lib_kinds() {
+        assert_eq!(native_args("foo"), vec!["-lfoo"]);
+        assert_eq!(native_args("dylib=foo"), vec!["-lfoo"]);
+        assert_eq!(native_args("framework=Cocoa"), vec!["-framework", "Cocoa"]);
+        assert_eq!(native_args("link-arg=-Wl,-z,now"), vec!["-Wl,-z,now"]);
+        assert_eq!(
+            native_args("static=foo"),
+            vec!["-Wl,--push-state,-Bstatic", "-lfoo", "-Wl,--pop-state"]
+        );
+    }
+
+    #[test]
+    fn native_lib_modifiers() {
+        assert_eq!(
+            native_args("static:+whole-archive,+verbatim=foo.o"),
+            vec![
+                "-Wl,--push-state,-Bstatic,--whole-archive",
+                "-l:foo.o",
+                "-Wl,--pop-state"
+            ]
+        );
+        assert_eq!(
+            native_args("dylib:+as-needed=foo"),
+            vec!["-Wl,--push-state,--as-needed", "-lfoo", "-Wl,--pop-state"]
+        );
+    }
+
+    #[test]
+    fn compile_once() {
+        let collection = parse("src/lib.rs", &["--crate-type", "staticlib,dylib,bin"]).unwrap();
+
+        assert_eq!(
+            artifact_paths(&collection),
+            vec![
+                Path::new("out/libfoo.a"),
+                Path::new("out/libfoo.so"),
+                Path::new("out/foo")
+            ]
+        );
+        assert!(collection
+            .args()
+            .compile_args()
+            .unwrap()
+            .contains(&String::from("-fPIC")));
+    }
+
+    #[test]
+    fn pipelined_metadata() {
+        let collection = parse(
+            "src/lib.rs",
+            &["--crate-type", "lib", "--emit=dep-info,metadata,link"],
+        )
+        .unwrap();
+        let args = collection.args();
+        let export = String::from("-frust-metadata-output=out/libfoo.rox");
+
+        assert!(args.pipelined());
+        assert!(args.metadata_args().unwrap().contains(&export));
+        // Lints run after type checking, and must not be skipped by the first pass
+        assert!(args
+            .metadata_args()
+            .unwrap()
+            .contains(&String::from("-frust-compile-until=compilation")));
+        assert!(!args.compile_args().unwrap().contains(&export));
+        // Warnings of the middle-end and of the backend only come from the second pass
+        assert!(!args.compile_args().unwrap().contains(&String::from("-w")));
+        assert_eq!(args.dep_file(), Some(Path::new("out/foo.gccrs.d")));
+    }
+
+    #[test]
+    fn check_metadata_only() {
+        let collection = parse(
+            "src/main.rs",
+            &["--crate-type", "bin", "--emit=dep-info,metadata"],
+        )
+        .unwrap();
+
+        assert_eq!(
+            artifact_paths(&collection),
+            vec![Path::new("out/libfoo.rmeta")]
+        );
+        assert!(collection.args().pipelined());
+        assert!(!collection.args().codegen());
+    }
+
+    #[test]
+    fn test_harness() {
+        assert!(matches!(
+            parse("src/lib.rs", &["--test"]),
+            Err(Error::TestHarness)
+        ));
+    }
+
+    #[test]
+    fn link_info_of_final_links_only() {
+        let dir = tempdir::TempDir::new("link-info").unwrap();
+        let upstream = dir.path().join("libbar.rlib");
+        let extern_crate = format!("bar={}", upstream.display());
+
+        // Link information which its pipelined crate is still writing
+        std::fs::write(upstream.with_extension("deps"), "archive").unwrap();
+
+        let collection = |crate_type| {
+            parse(
+                "src/lib.rs",
+                &["--crate-type", crate_type, "--extern", &extern_crate],
+            )
+        };
+
+        assert!(collection("rlib").is_ok());
+        assert!(collection("bin").is_err());
+    }
+
+    #[test]
+    fn language_options() {
+        let collection = parse(
+            "src/lib.rs",
+            &[
+                "--edition=2021",
+                "--cfg",
+                "feature=\"std\"",
+                "-C",
+                "panic=abort",
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            collection.args().language_args,
+            vec![
+                "-frust-edition=2021",
+                "-frust-cfg=feature=\"std\"",
+                "-frust-cfg=gccrs",
+                "-frust-cfg=panic=\"abort\""
+            ]
+        );
+        // Implied configuration options are only given once
+        assert_eq!(
+            collection
+                .args()
+                .frontend_args()
+                .unwrap()
+                .iter()
+                .filter(|arg| *arg == "-frust-cfg=debug_assertions")
+                .count(),
+            1
+        );
+        assert!(parse("src/lib.rs", &["--edition=2024"]).is_err());
+
+        // Configuration options given explicitly are kept when not implied
+        assert!(parse(
+            "src/lib.rs",
+            &["--cfg", "debug_assertions", "-C", "opt-level=3"]
+        )
+        .unwrap()
+        .args()
+        .language_args
+        .contains(&String::from("-frust-cfg=debug_assertions")));
+    }
+
+    #[test]
+    fn profile_options() {
+        let release = CodegenOptions {
+            opt_level: Some(OptLevel::Three),
+            debuginfo: Some(DebugInfo::LineTablesOnly),
+            ..Default::default()
+        };
+        let debug = CodegenOptions {
+            debuginfo: Some(DebugInfo::Full),
+            panic: Some(PanicStrategy::Abort),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Args::codegen_options(&release),
+            vec!["-O3", "-g1", "-frust-panic=unwind", "-fexceptions"]
+        );
+        assert_eq!(
+            Args::codegen_options(&debug),
+            vec![
+                "-g2",
+                "-frust-cfg=debug_assertions",
+                "-frust-panic=abort",
+                "-fno-exceptions"
+            ]
+        );
+    }
+
+    #[test]
+    fn relocation_models() {
+        let artifact = |crate_type| Artifact {
+            emit: EmitKind::Link,
+            crate_type: Some(crate_type),
+            path: PathBuf::new(),
+        };
+        let bin = [artifact(CrateType::Bin)];
+        let rlib = [artifact(CrateType::Rlib)];
+        let dylib = [artifact(CrateType::Bin), artifact(CrateType::DyLib)];
+
+        assert_eq!(
+            Args::object_relocation(RelocModel::Pic, &bin),
+            RelocModel::Pie
+        );
+        assert_eq!(
+            Args::object_relocation(RelocModel::Pic, &rlib),
+            RelocModel::Pic
+        );
+        assert_eq!(
+            Args::object_relocation(RelocModel::Static, &bin),
+            RelocModel::Static
+        );
+        assert_eq!(
+            Args::object_relocation(RelocModel::Static, &dylib),
+            RelocModel::Pic
+        );
+    }
+
+    #[test]
+    fn linkers() {
+        assert_eq!(
+            Args::linker(Some(Path::new("/opt/mold/bin/mold"))),
+            (
+                None,
+                vec![
+                    String::from("-fuse-ld=mold"),
+                    String::from("-B/opt/mold/bin")
+                ]
+            )
+        );
+        assert_eq!(
+            Args::linker(Some(Path::new("ld.lld"))),
+            (None, vec![String::from("-fuse-ld=lld")])
+        );
+        assert_eq!(
+            Args::linker(Some(Path::new("aarch64-linux-gnu-gcc"))),
+            (Some(PathBuf::from("aarch64-linux-gnu-gcc")), vec![])
+        );
+
+        assert!(parse("src/main.rs", &["-C", "link-self-contained=yes"]).is_ok());
+        assert!(parse("src/main.rs", &["-C", "link-self-contained=no"]).is_err());
+    }
+
+    #[test]
+    fn cargo_lto_profiles() {
+        // LTO options used when compiling the crate, and when linking its executable
+        let lto_args = |input, args: &[&str]| -> (Vec<String>, Vec<String>) {
+            let collection = parse(input, args).unwrap();
+            let is_lto = |arg: &String| arg.contains("lto");
+            let link_args = match collection.artifacts().first() {
+                Some(artifact) if artifact.crate_type == Some(CrateType::Bin) => collection
+                    .args()
+                    .link_args_for(artifact, CrateType::Bin)
+                    .unwrap(),
+                _ => vec![],
+            };
+
+            (
+                collection
+                    .args()
+                    .compile_args()
+                    .unwrap()
+                    .into_iter()
+                    .filter(is_lto)
+                    .collect(),
+                link_args.into_iter().filter(is_lto).collect(),
+            )
+        };
+        let lib = ["--crate-type", "lib", "--emit=dep-info,metadata,link"];
+        let bin = ["--crate-type", "bin", "--emit=dep-info,link"];
+        let fat = vec!["-flto=auto", "-flto-partition=one"];
+
+        // `lto = true`: Dependencies only linked with LTO get `-C linker-plugin-lto`,
+        // the other ones no option at all
+        for extra in [&["-C", "linker-plugin-lto"][..], &[]] {
+            let args = [&lib[..], &["-C", "opt-level=3"], extra].concat();
+            assert_eq!(
+                lto_args("src/lib.rs", &args).0,
+                vec!["-flto=auto", "-ffat-lto-objects"]
+            );
+        }
+        let args = [&bin[..], &["-C", "opt-level=3", "-C", "lto"]].concat();
+        let (compile, link) = lto_args("src/main.rs", &args);
+        assert_eq!(compile, fat);
+        assert_eq!(link, fat);
+
+        // `lto = false`
+        let args = [&lib[..], &["-C", "opt-level=3", "-C", "embed-bitcode=no"]].concat();
+        assert!(lto_args("src/lib.rs", &args).0.is_empty());
+        let args = [&bin[..], &["-C", "opt-level=3"]].concat();
+        let (compile, link) = lto_args("src/main.rs", &args);
+        assert!(compile.is_empty() && link.is_empty());
+
+        // Static libraries are linked by C toolchains, which may not optimize at link time
+        assert_eq!(
+            lto_args("src/lib.rs", &["--crate-type", "staticlib", "-C", "lto"]).0,
+            [&fat[..], &["-ffat-lto-objects"]].concat()
+        );
+    }
+
+    #[test]
+    fn rlib_without_lto() {
+        let collection = parse(
+            "src/lib.rs",
+            &["--crate-type", "rlib", "-C", "embed-bitcode=no"],
+        )
+        .unwrap();
+        let args = collection.args();
+
+        assert!(!args
+            .compile_args()
+            .unwrap()
+            .iter()
+            .any(|arg| arg.starts_with("-flto")));
+        assert_eq!(args.archiver(), "ar");
+        assert_eq!(
+            parse("src/lib.rs", &["--crate-type", "rlib"])
+                .unwrap()
+                .args()
+                .archiver(),
+            "gcc-ar"
+        );
+    }
+
+    #[test]
+    fn profile_guided_optimization() {
+        let generate = CodegenOptions {
+            profile_generate: Some(PathBuf::from("/tmp/pgo")),
+            ..Default::default()
+        };
+        let data = CodegenOptions {
+            profile_use: Some(PathBuf::from("/tmp/pgo")),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Args::profile(&generate).unwrap(),
+            vec!["-fprofile-generate=/tmp/pgo"]
+        );
+        assert_eq!(
+            Args::profile(&data).unwrap(),
+            vec![
+                "-fprofile-use=/tmp/pgo",
+                "-fprofile-correction",
+                "-Wno-missing-profile"
+            ]
+        );
+        assert!(Args::profile(&CodegenOptions::default())
+            .unwrap()
+            .is_empty());
+
+        // Crates with the same name only differ by their metadata hash
+        let profile_name = |metadata: &str| {
+            parse(
+                "src/lib.rs",
+                &[
+                    "--crate-type",
+                    "lib",
+                    "-C",
+                    metadata,
+                    "-C",
+                    "profile-generate=/tmp/pgo",
+                ],
+            )
+            .unwrap()
+            .args()
+            .profile_name
+            .clone()
+        };
+        assert_eq!(profile_name("metadata=1234"), "foo-lib-1234");
+        assert_ne!(profile_name("metadata=1234"), profile_name("metadata=5678"));
+    }
+
+    #[test]
+    fn packed_debuginfo() {
+        let collection = parse(
+            "src/main.rs",
+            &[
+                "-C",
+                "debuginfo=2",
+                "-C",
+                "split-debuginfo=packed",
+                "-C",
+                "strip=symbols",
+            ],
+        )
+        .unwrap();
+        let args = collection.args();
+        let artifact = &collection.artifacts()[0];
+
+        assert_eq!(
+            args.debuginfo_file(artifact),
+            Some(PathBuf::from("out/foo.debug"))
+        );
+        assert_eq!(args.strip_option(), Some("--strip-all"));
+        assert!(!args
+            .link_args_for(artifact, CrateType::Bin)
+            .unwrap()
+            .contains(&String::from("-s")));
+    }
+
+    #[test]
+    fn instrument_coverage() {
+        let collection = parse(
+            "src/main.rs",
+            &["-C", "extra-filename=-1234", "-C", "instrument-coverage"],
+        )
+        .unwrap();
+        let args = collection.args();
+        let frontend = args.frontend_args().unwrap();
+
+        // Notes are written next to the crate's outputs, named after them
+        let dumpbase = frontend.iter().position(|arg| arg == "-dumpbase").unwrap();
+        assert_eq!(frontend[dumpbase + 1], "out/foo-1234");
+        assert!(frontend.contains(&String::from("--coverage")));
+        assert!(args
+            .link_args_for(&collection.artifacts()[0], CrateType::Bin)
+            .unwrap()
+            .contains(&String::from("--coverage")));
+    }
+}
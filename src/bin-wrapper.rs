@@ -3,7 +3,17 @@
 //! instead of `rustc`
 
 use anyhow::{anyhow, Result};
-use cargo_gccrs::Error;
+use cargo_gccrs::{coverage::CoverageReport, Error};
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Directory in which `cargo gccrs pgo` collects profile data, inside of the target
+/// directory
+const PGO_DIR: &str = "gccrs-pgo";
+
+/// Directory in which `cargo gccrs cov` writes its report, inside of the target directory
+const COV_DIR: &str = "gccrs-cov";
 
 /// Create a new `cargo` process with `cargo-gccrs` set as the RUSTC_WRAPPER environment
 /// variable. This causes `cargo` to invoke this binary as a compiler, which we can
@@ -11,9 +21,19 @@ use cargo_gccrs::Error;
 pub fn spawn() -> Result<(), Error> {
     // Skip `cargo` and `gccrs` in the invocation. Since we spawn a new cargo command,
     // `cargo gccrs arg0 arg1` will become `cargo run arg0 arg1`
-    let mut cargo_gccrs = std::process::Command::new("cargo")
+    let args: Vec<String> = std::env::args().skip(2).collect();
+
+    match args.first().map(String::as_str) {
+        Some("pgo") => pgo(&args[1..]),
+        Some("cov") => cov(&args[1..]),
+        _ => cargo(Command::new("cargo").args(args)),
+    }
+}
+
+/// Run a `cargo` command using `gccrs-driver` as the compiler
+fn cargo(cmd: &mut Command) -> Result<(), Error> {
+    let mut cargo_gccrs = cmd
         .env("RUSTC_WRAPPER", "gccrs-driver")
-        .args(std::env::args().skip(2))
         .spawn()
         .map_err(|_| Error::WrapperLaunch)?;
 
@@ -27,6 +47,210 @@ pub fn spawn() -> Result<(), Error> {
     }
 }
 
+/// Get the flags given to every `rustc` invocation through the environment, if any
+fn env_rustflags() -> Option<Vec<String>> {
+    if let Ok(flags) = std::env::var("CARGO_ENCODED_RUSTFLAGS") {
+        return Some(
+            flags
+                .split('\x1f')
+                .filter(|flag| !flag.is_empty())
+                .map(str::to_owned)
+                .collect(),
+        );
+    }
+
+    std::env::var("RUSTFLAGS")
+        .ok()
+        .map(|flags| flags.split_whitespace().map(str::to_owned).collect())
+}
+
+/// Run `cargo build` with an extra flag given to every `rustc` invocation. Flags given
+/// through the environment replace the ones of `cargo`'s configuration, and the flag is
+/// added to them. Otherwise, it is added to `build.rustflags`, which extends the flags
+/// of the configuration files
+fn build_with_rustflag(build_args: &[String], flag: &str) -> Result<(), Error> {
+    let mut cmd = Command::new("cargo");
+    cmd.arg("build").args(build_args);
+
+    match env_rustflags() {
+        Some(mut rustflags) => {
+            rustflags.push(flag.to_owned());
+            cmd.env_remove("RUSTFLAGS")
+                .env("CARGO_ENCODED_RUSTFLAGS", rustflags.join("\x1f"));
+        }
+        None => {
+            let rustflags = serde_json::to_string(&[flag]).map_err(std::io::Error::from)?;
+            cmd.arg("--config")
+                .arg(format!("build.rustflags={rustflags}"));
+        }
+    }
+
+    cargo(&mut cmd)
+}
+
+/// Get the value of an option given to `cargo`, as either `--option value` or
+/// `--option=value`
+fn cargo_option<'a>(args: &'a [String], option: &str) -> Option<&'a str> {
+    let prefix = format!("{option}=");
+
+    args.iter()
+        .enumerate()
+        .find_map(|(i, arg)| match arg.strip_prefix(&prefix) {
+            Some(value) => Some(value),
+            None if arg == option => args.get(i + 1).map(String::as_str),
+            None => None,
+        })
+}
+
+/// Get a directory of the workspace `cargo` builds with the given arguments, as reported
+/// by `cargo metadata`
+fn workspace_dir(cargo_args: &[String], key: &str) -> Result<PathBuf, Error> {
+    let mut metadata = Command::new("cargo");
+    metadata.args(["metadata", "--format-version", "1", "--no-deps"]);
+    if let Some(manifest_path) = cargo_option(cargo_args, "--manifest-path") {
+        metadata.args(["--manifest-path", manifest_path]);
+    }
+
+    let output = metadata.output()?;
+    if !output.status.success() {
+        return Err(Error::Metadata);
+    }
+
+    serde_json::from_slice::<serde_json::Value>(&output.stdout)
+        .ok()
+        .and_then(|metadata| Some(PathBuf::from(metadata.get(key)?.as_str()?)))
+        .ok_or(Error::Metadata)
+}
+
+/// Get the absolute path of the target directory `cargo` will use with the given
+/// arguments. Unless given explicitly, `cargo metadata` finds it in the environment, the
+/// configuration files or the workspace
+fn target_dir(cargo_args: &[String]) -> Result<PathBuf, Error> {
+    match cargo_option(cargo_args, "--target-dir") {
+        Some(target_dir) => Ok(std::env::current_dir()?.join(target_dir)),
+        None => workspace_dir(cargo_args, "target_directory"),
+    }
+}
+
+/// Split the arguments of a subcommand building the project then running a command,
+/// `[BUILD ARGS...] -- <COMMAND...>`
+fn split_command(args: &[String]) -> Result<(&[String], &String, &[String]), Error> {
+    let separator = args
+        .iter()
+        .position(|arg| arg == "--")
+        .ok_or(Error::Invocation)?;
+    let (program, program_args) = args[separator + 1..]
+        .split_first()
+        .ok_or(Error::Invocation)?;
+
+    Ok((&args[..separator], program, program_args))
+}
+
+/// Recursively collect the files with a given extension inside of a directory
+fn find_files(dir: &Path, extension: &str, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_files(&path, extension, files)?;
+        } else if path.extension().is_some_and(|ext| ext == extension) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Build the project with profile-guided optimizations:
+/// `cargo gccrs pgo [BUILD ARGS...] -- <TRAINING COMMAND...>` first builds an
+/// instrumented version of the project, then runs the training command to collect
+/// profile data, and finally builds the project again using that data
+fn pgo(args: &[String]) -> Result<(), Error> {
+    let (build_args, program, program_args) = split_command(args)?;
+
+    let profile_dir = target_dir(build_args)?.join(PGO_DIR);
+
+    // Start from scratch, as stale profile data would not match the new instrumentation
+    if profile_dir.exists() {
+        std::fs::remove_dir_all(&profile_dir)?;
+    }
+    std::fs::create_dir_all(&profile_dir)?;
+
+    let build = |flag: String| build_with_rustflag(build_args, &flag);
+
+    build(format!("-Cprofile-generate={}", profile_dir.display()))?;
+
+    let trained = Command::new(program)
+        .args(program_args)
+        .status()
+        .map_err(|_| Error::PgoTraining)?
+        .success();
+    if !trained {
+        return Err(Error::PgoTraining);
+    }
+
+    build(format!("-Cprofile-use={}", profile_dir.display()))
+}
+
+/// Measure the code coverage of a program built from the project:
+/// `cargo gccrs cov [BUILD ARGS...] -- <COMMAND...>` builds the project with
+/// `-C instrument-coverage` and runs the command, such as the built executable or a
+/// script exercising it. The data collected by `gcov` is then converted into an `lcov`
+/// tracefile, and a summary of the covered lines is printed
+fn cov(args: &[String]) -> Result<(), Error> {
+    let (build_args, program, program_args) = split_command(args)?;
+
+    let workspace = workspace_dir(build_args, "workspace_root")?;
+    let target_dir = target_dir(build_args)?;
+
+    // Execution counts accumulate in existing data files across runs
+    let mut stale_data = Vec::new();
+    find_files(&target_dir, "gcda", &mut stale_data)?;
+    for data in stale_data.iter() {
+        std::fs::remove_file(data)?;
+    }
+
+    build_with_rustflag(build_args, "-Cinstrument-coverage")?;
+
+    let ran = Command::new(program)
+        .args(program_args)
+        .status()
+        .map_err(|_| Error::CoverageRun)?
+        .success();
+    if !ran {
+        return Err(Error::CoverageRun);
+    }
+
+    let mut data_files = Vec::new();
+    find_files(&target_dir, "gcda", &mut data_files)?;
+
+    let mut report = CoverageReport::default();
+    for data in data_files.iter() {
+        let output = Command::new("gcov")
+            .args(["--json-format", "--stdout"])
+            .arg(data)
+            .output()
+            .map_err(|_| Error::Coverage)?;
+        if !output.status.success() {
+            return Err(Error::Coverage);
+        }
+
+        report.add_gcov_json(&String::from_utf8_lossy(&output.stdout))?;
+    }
+    report.retain_under(&workspace);
+
+    let report_dir = target_dir.join(COV_DIR);
+    std::fs::create_dir_all(&report_dir)?;
+    std::fs::write(report_dir.join("lcov.info"), report.to_lcov())?;
+
+    print!("{}", report.summary(&workspace));
+
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let args: Vec<String> = std::env::args().collect();
 
@@ -0,0 +1,802 @@
+//! Typed representation of the `-C` codegen options accepted by `rustc`. Each option
+//! given on the command line is parsed into its corresponding field, and can be
+//! serialized back into a `-C <option>=<value>` argument.
+
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::path::PathBuf;
+
+use super::{Error, Result};
+
+/// Optimization levels accepted by `-C opt-level`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptLevel {
+    Zero,
+    One,
+    Two,
+    Three,
+    /// Optimize for binary size
+    Size,
+    /// Optimize for binary size, but also turn off loop vectorization
+    SizeMin,
+}
+
+impl TryFrom<&str> for OptLevel {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<OptLevel> {
+        match s {
+            "0" => Ok(OptLevel::Zero),
+            "1" => Ok(OptLevel::One),
+            "2" => Ok(OptLevel::Two),
+            "3" => Ok(OptLevel::Three),
+            "s" => Ok(OptLevel::Size),
+            "z" => Ok(OptLevel::SizeMin),
+            _ => Err(invalid_value("opt-level", s)),
+        }
+    }
+}
+
+impl Display for OptLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            OptLevel::Zero => "0",
+            OptLevel::One => "1",
+            OptLevel::Two => "2",
+            OptLevel::Three => "3",
+            OptLevel::Size => "s",
+            OptLevel::SizeMin => "z",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// Amount of debug information accepted by `-C debuginfo`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugInfo {
+    None,
+    LineDirectivesOnly,
+    LineTablesOnly,
+    Limited,
+    Full,
+}
+
+impl TryFrom<&str> for DebugInfo {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<DebugInfo> {
+        match s {
+            "0" | "none" => Ok(DebugInfo::None),
+            "line-directives-only" => Ok(DebugInfo::LineDirectivesOnly),
+            "line-tables-only" => Ok(DebugInfo::LineTablesOnly),
+            "1" | "limited" => Ok(DebugInfo::Limited),
+            "2" | "full" => Ok(DebugInfo::Full),
+            _ => Err(invalid_value("debuginfo", s)),
+        }
+    }
+}
+
+impl Display for DebugInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            DebugInfo::None => "0",
+            DebugInfo::LineDirectivesOnly => "line-directives-only",
+            DebugInfo::LineTablesOnly => "line-tables-only",
+            DebugInfo::Limited => "1",
+            DebugInfo::Full => "2",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// Link-time optimization modes accepted by `-C lto`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lto {
+    Off,
+    Thin,
+    Fat,
+}
+
+impl TryFrom<Option<&str>> for Lto {
+    type Error = Error;
+
+    fn try_from(s: Option<&str>) -> Result<Lto> {
+        match s {
+            None | Some("y") | Some("yes") | Some("on") | Some("true") | Some("fat") => {
+                Ok(Lto::Fat)
+            }
+            Some("n") | Some("no") | Some("off") | Some("false") => Ok(Lto::Off),
+            Some("thin") => Ok(Lto::Thin),
+            Some(s) => Err(invalid_value("lto", s)),
+        }
+    }
+}
+
+impl Display for Lto {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            Lto::Off => "off",
+            Lto::Thin => "thin",
+            Lto::Fat => "fat",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// Panic strategies accepted by `-C panic`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanicStrategy {
+    Unwind,
+    Abort,
+}
+
+impl TryFrom<&str> for PanicStrategy {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<PanicStrategy> {
+        match s {
+            "unwind" => Ok(PanicStrategy::Unwind),
+            "abort" => Ok(PanicStrategy::Abort),
+            _ => Err(invalid_value("panic", s)),
+        }
+    }
+}
+
+impl Display for PanicStrategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            PanicStrategy::Unwind => "unwind",
+            PanicStrategy::Abort => "abort",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// Relocation models accepted by `-C relocation-model`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocModel {
+    Static,
+    Pic,
+    Pie,
+    DynamicNoPic,
+    Ropi,
+    Rwpi,
+    RopiRwpi,
+    Default,
+}
+
+impl TryFrom<&str> for RelocModel {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<RelocModel> {
+        match s {
+            "static" => Ok(RelocModel::Static),
+            "pic" => Ok(RelocModel::Pic),
+            "pie" => Ok(RelocModel::Pie),
+            "dynamic-no-pic" => Ok(RelocModel::DynamicNoPic),
+            "ropi" => Ok(RelocModel::Ropi),
+            "rwpi" => Ok(RelocModel::Rwpi),
+            "ropi-rwpi" => Ok(RelocModel::RopiRwpi),
+            "default" => Ok(RelocModel::Default),
+            _ => Err(invalid_value("relocation-model", s)),
+        }
+    }
+}
+
+impl Display for RelocModel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            RelocModel::Static => "static",
+            RelocModel::Pic => "pic",
+            RelocModel::Pie => "pie",
+            RelocModel::DynamicNoPic => "dynamic-no-pic",
+            RelocModel::Ropi => "ropi",
+            RelocModel::Rwpi => "rwpi",
+            RelocModel::RopiRwpi => "ropi-rwpi",
+            RelocModel::Default => "default",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// Debug information splitting modes accepted by `-C split-debuginfo`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitDebuginfo {
+    Off,
+    Packed,
+    Unpacked,
+}
+
+impl TryFrom<&str> for SplitDebuginfo {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<SplitDebuginfo> {
+        match s {
+            "off" => Ok(SplitDebuginfo::Off),
+            "packed" => Ok(SplitDebuginfo::Packed),
+            "unpacked" => Ok(SplitDebuginfo::Unpacked),
+            _ => Err(invalid_value("split-debuginfo", s)),
+        }
+    }
+}
+
+impl Display for SplitDebuginfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            SplitDebuginfo::Off => "off",
+            SplitDebuginfo::Packed => "packed",
+            SplitDebuginfo::Unpacked => "unpacked",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// Stripping modes accepted by `-C strip`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strip {
+    None,
+    Debuginfo,
+    Symbols,
+}
+
+impl TryFrom<&str> for Strip {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Strip> {
+        match s {
+            "none" => Ok(Strip::None),
+            "debuginfo" => Ok(Strip::Debuginfo),
+            "symbols" => Ok(Strip::Symbols),
+            _ => Err(invalid_value("strip", s)),
+        }
+    }
+}
+
+impl Display for Strip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            Strip::None => "none",
+            Strip::Debuginfo => "debuginfo",
+            Strip::Symbols => "symbols",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// Sanitizers accepted by `-C sanitize` and `-Z sanitizer`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sanitizer {
+    Address,
+    Cfi,
+    Dataflow,
+    HwAddress,
+    Kcfi,
+    KernelAddress,
+    Leak,
+    Memory,
+    Memtag,
+    Safestack,
+    ShadowCallStack,
+    Thread,
+    /// Undefined behavior checks, which only GCC provides
+    Undefined,
+}
+
+impl TryFrom<&str> for Sanitizer {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Sanitizer> {
+        match s {
+            "address" => Ok(Sanitizer::Address),
+            "cfi" => Ok(Sanitizer::Cfi),
+            "dataflow" => Ok(Sanitizer::Dataflow),
+            "hwaddress" => Ok(Sanitizer::HwAddress),
+            "kcfi" => Ok(Sanitizer::Kcfi),
+            "kernel-address" => Ok(Sanitizer::KernelAddress),
+            "leak" => Ok(Sanitizer::Leak),
+            "memory" => Ok(Sanitizer::Memory),
+            "memtag" => Ok(Sanitizer::Memtag),
+            "safestack" => Ok(Sanitizer::Safestack),
+            "shadow-call-stack" => Ok(Sanitizer::ShadowCallStack),
+            "thread" => Ok(Sanitizer::Thread),
+            "undefined" => Ok(Sanitizer::Undefined),
+            _ => Err(invalid_value("sanitize", s)),
+        }
+    }
+}
+
+impl Display for Sanitizer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            Sanitizer::Address => "address",
+            Sanitizer::Cfi => "cfi",
+            Sanitizer::Dataflow => "dataflow",
+            Sanitizer::HwAddress => "hwaddress",
+            Sanitizer::Kcfi => "kcfi",
+            Sanitizer::KernelAddress => "kernel-address",
+            Sanitizer::Leak => "leak",
+            Sanitizer::Memory => "memory",
+            Sanitizer::Memtag => "memtag",
+            Sanitizer::Safestack => "safestack",
+            Sanitizer::ShadowCallStack => "shadow-call-stack",
+            Sanitizer::Thread => "thread",
+            Sanitizer::Undefined => "undefined",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// A single target feature given to `-C target-feature`, such as `+avx2` or `-sse4.1`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetFeature {
+    pub name: String,
+    pub enabled: bool,
+}
+
+impl TryFrom<&str> for TargetFeature {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<TargetFeature> {
+        let (enabled, name) = match s.split_at(s.len().min(1)) {
+            ("+", name) if !name.is_empty() => (true, name),
+            ("-", name) if !name.is_empty() => (false, name),
+            _ => return Err(invalid_value("target-feature", s)),
+        };
+
+        Ok(TargetFeature {
+            name: name.to_owned(),
+            enabled,
+        })
+    }
+}
+
+impl Display for TargetFeature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let sign = if self.enabled { '+' } else { '-' };
+
+        write!(f, "{sign}{}", self.name)
+    }
+}
+
+/// All the stable codegen options that can be given to `rustc` using `-C`. Options which
+/// were not present on the command line are left to `None`, or empty
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CodegenOptions {
+    pub ar: Option<String>,
+    pub code_model: Option<String>,
+    pub codegen_units: Option<usize>,
+    pub collapse_macro_debuginfo: Option<String>,
+    pub control_flow_guard: Option<String>,
+    pub debug_assertions: Option<bool>,
+    pub debuginfo: Option<DebugInfo>,
+    pub default_linker_libraries: Option<bool>,
+    pub dlltool: Option<PathBuf>,
+    pub embed_bitcode: Option<bool>,
+    pub extra_filename: Option<String>,
+    pub force_frame_pointers: Option<bool>,
+    pub force_unwind_tables: Option<bool>,
+    pub incremental: Option<PathBuf>,
+    pub inline_threshold: Option<u32>,
+    pub instrument_coverage: Option<bool>,
+    /// Arguments given through both `-C link-arg` and `-C link-args`, in order
+    pub link_args: Vec<String>,
+    pub link_dead_code: Option<bool>,
+    pub link_self_contained: Option<bool>,
+    pub linker: Option<PathBuf>,
+    pub linker_flavor: Option<String>,
+    pub linker_plugin_lto: Option<String>,
+    pub llvm_args: Vec<String>,
+    pub lto: Option<Lto>,
+    pub metadata: Vec<String>,
+    pub no_prepopulate_passes: Option<bool>,
+    pub no_redzone: Option<bool>,
+    pub no_stack_check: Option<bool>,
+    pub no_vectorize_loops: Option<bool>,
+    pub no_vectorize_slp: Option<bool>,
+    pub opt_level: Option<OptLevel>,
+    pub overflow_checks: Option<bool>,
+    pub panic: Option<PanicStrategy>,
+    pub passes: Vec<String>,
+    pub prefer_dynamic: Option<bool>,
+    pub profile_generate: Option<PathBuf>,
+    pub profile_use: Option<PathBuf>,
+    pub relocation_model: Option<RelocModel>,
+    pub relro_level: Option<String>,
+    pub remark: Vec<String>,
+    pub rpath: Option<bool>,
+    /// Sanitizers given through both `-C sanitize` and `-Z sanitizer`, without duplicates
+    pub sanitizers: Vec<Sanitizer>,
+    pub save_temps: Option<bool>,
+    pub soft_float: Option<bool>,
+    pub split_debuginfo: Option<SplitDebuginfo>,
+    pub strip: Option<Strip>,
+    pub symbol_mangling_version: Option<String>,
+    pub target_cpu: Option<String>,
+    pub target_features: Vec<TargetFeature>,
+    pub unsafe_allow_abi_mismatch: Vec<String>,
+}
+
+fn invalid_value(key: &str, value: &str) -> Error {
+    Error::InvalidArg(format!(
+        "invalid value `{value}` for codegen option `{key}`"
+    ))
+}
+
+fn required<'a>(key: &str, value: Option<&'a str>) -> Result<&'a str> {
+    value.ok_or_else(|| Error::InvalidArg(format!("codegen option `{key}` requires a value")))
+}
+
+/// Parse a boolean codegen option. Like `rustc`, a missing value means `true`
+fn parse_bool(key: &str, value: Option<&str>) -> Result<bool> {
+    match value {
+        None | Some("y") | Some("yes") | Some("on") | Some("true") => Ok(true),
+        Some("n") | Some("no") | Some("off") | Some("false") => Ok(false),
+        Some(s) => Err(invalid_value(key, s)),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(key: &str, value: Option<&str>) -> Result<T> {
+    let value = required(key, value)?;
+
+    value.parse().map_err(|_| invalid_value(key, value))
+}
+
+fn parse_string(key: &str, value: Option<&str>) -> Result<String> {
+    required(key, value).map(str::to_owned)
+}
+
+fn parse_list(key: &str, value: Option<&str>) -> Result<Vec<String>> {
+    Ok(required(key, value)?
+        .split_whitespace()
+        .map(str::to_owned)
+        .collect())
+}
+
+impl CodegenOptions {
+    /// Parse and apply a single `-C` option, of the form `KEY[=VALUE]`
+    pub fn apply(&mut self, option: &str) -> Result {
+        let mut split = option.splitn(2, '=');
+        // `rustc` accepts both dashes and underscores in option names
+        let key = split.next().unwrap_or_default().replace('_', "-");
+        let value = split.next();
+        let k = key.as_str();
+
+        match k {
+            "ar" => self.ar = Some(parse_string(k, value)?),
+            "code-model" => self.code_model = Some(parse_string(k, value)?),
+            "codegen-units" => self.codegen_units = Some(parse_number(k, value)?),
+            "collapse-macro-debuginfo" => {
+                self.collapse_macro_debuginfo = Some(parse_string(k, value)?)
+            }
+            "control-flow-guard" => self.control_flow_guard = Some(parse_string(k, value)?),
+            "debug-assertions" => self.debug_assertions = Some(parse_bool(k, value)?),
+            "debuginfo" => self.debuginfo = Some(DebugInfo::try_from(required(k, value)?)?),
+            "default-linker-libraries" => {
+                self.default_linker_libraries = Some(parse_bool(k, value)?)
+            }
+            "dlltool" => self.dlltool = Some(PathBuf::from(required(k, value)?)),
+            "embed-bitcode" => self.embed_bitcode = Some(parse_bool(k, value)?),
+            "extra-filename" => self.extra_filename = Some(parse_string(k, value)?),
+            "force-frame-pointers" => self.force_frame_pointers = Some(parse_bool(k, value)?),
+            "force-unwind-tables" => self.force_unwind_tables = Some(parse_bool(k, value)?),
+            "incremental" => self.incremental = Some(PathBuf::from(required(k, value)?)),
+            "inline-threshold" => self.inline_threshold = Some(parse_number(k, value)?),
+            "instrument-coverage" => {
+                self.instrument_coverage = Some(match value {
+                    Some("all") => true,
+                    _ => parse_bool(k, value)?,
+                })
+            }
+            "link-arg" => self.link_args.push(parse_string(k, value)?),
+            "link-args" => self.link_args.append(&mut parse_list(k, value)?),
+            "link-dead-code" => self.link_dead_code = Some(parse_bool(k, value)?),
+            "link-self-contained" => self.link_self_contained = Some(parse_bool(k, value)?),
+            "linker" => self.linker = Some(PathBuf::from(required(k, value)?)),
+            "linker-flavor" => self.linker_flavor = Some(parse_string(k, value)?),
+            "linker-plugin-lto" => self.linker_plugin_lto = Some(value.unwrap_or("y").to_owned()),
+            "llvm-args" => self.llvm_args.append(&mut parse_list(k, value)?),
+            "lto" => self.lto = Some(Lto::try_from(value)?),
+            "metadata" => self.metadata.push(parse_string(k, value)?),
+            "no-prepopulate-passes" => self.no_prepopulate_passes = Some(parse_bool(k, value)?),
+            "no-redzone" => self.no_redzone = Some(parse_bool(k, value)?),
+            "no-stack-check" => self.no_stack_check = Some(parse_bool(k, value)?),
+            "no-vectorize-loops" => self.no_vectorize_loops = Some(parse_bool(k, value)?),
+            "no-vectorize-slp" => self.no_vectorize_slp = Some(parse_bool(k, value)?),
+            "opt-level" => self.opt_level = Some(OptLevel::try_from(required(k, value)?)?),
+            "overflow-checks" => self.overflow_checks = Some(parse_bool(k, value)?),
+            "panic" => self.panic = Some(PanicStrategy::try_from(required(k, value)?)?),
+            "passes" => self.passes.append(&mut parse_list(k, value)?),
+            "prefer-dynamic" => self.prefer_dynamic = Some(parse_bool(k, value)?),
+            "profile-generate" => self.profile_generate = Some(PathBuf::from(value.unwrap_or("."))),
+            "profile-use" => self.profile_use = Some(PathBuf::from(required(k, value)?)),
+            "relocation-model" => {
+                self.relocation_model = Some(RelocModel::try_from(required(k, value)?)?)
+            }
+            "relro-level" => self.relro_level = Some(parse_string(k, value)?),
+            "remark" => self.remark.append(&mut parse_list(k, value)?),
+            "rpath" => self.rpath = Some(parse_bool(k, value)?),
+            "sanitize" => {
+                for sanitizer in required(k, value)?.split(',').filter(|s| !s.is_empty()) {
+                    let sanitizer = Sanitizer::try_from(sanitizer)?;
+                    if !self.sanitizers.contains(&sanitizer) {
+                        self.sanitizers.push(sanitizer);
+                    }
+                }
+            }
+            "save-temps" => self.save_temps = Some(parse_bool(k, value)?),
+            "soft-float" => self.soft_float = Some(parse_bool(k, value)?),
+            "split-debuginfo" => {
+                self.split_debuginfo = Some(SplitDebuginfo::try_from(required(k, value)?)?)
+            }
+            "strip" => self.strip = Some(Strip::try_from(required(k, value)?)?),
+            "symbol-mangling-version" => {
+                self.symbol_mangling_version = Some(parse_string(k, value)?)
+            }
+            "target-cpu" => self.target_cpu = Some(parse_string(k, value)?),
+            "target-feature" => {
+                for feature in required(k, value)?.split(',').filter(|f| !f.is_empty()) {
+                    self.target_features.push(TargetFeature::try_from(feature)?);
+                }
+            }
+            "unsafe-allow-abi-mismatch" => {
+                self.unsafe_allow_abi_mismatch.push(parse_string(k, value)?)
+            }
+            _ => {
+                return Err(Error::InvalidArg(format!(
+                    "unknown codegen option: `{key}`"
+                )))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check if a target feature was explicitly enabled or disabled. The last occurence
+    /// of the feature wins
+    pub fn target_feature(&self, name: &str) -> Option<bool> {
+        self.target_features
+            .iter()
+            .rev()
+            .find(|feature| feature.name == name)
+            .map(|feature| feature.enabled)
+    }
+
+    /// Get the panic strategy of the crate. Like `rustc`, default to unwinding
+    pub fn panic_strategy(&self) -> PanicStrategy {
+        self.panic.unwrap_or(PanicStrategy::Unwind)
+    }
+
+    /// Check if debug assertions are enabled. Like `rustc`, they are unless the crate is
+    /// optimized or they are explicitly disabled
+    pub fn debug_assertions_enabled(&self) -> bool {
+        self.debug_assertions
+            .unwrap_or(matches!(self.opt_level, None | Some(OptLevel::Zero)))
+    }
+
+    /// Check if `-C linker-plugin-lto` is enabled. Its value is either a boolean, or the
+    /// path of the linker plugin to use
+    pub fn linker_plugin_lto_enabled(&self) -> bool {
+        self.linker_plugin_lto
+            .as_deref()
+            .is_some_and(|value| !matches!(value, "n" | "no" | "off" | "false"))
+    }
+
+    /// Check if arithmetic overflows are checked. Unless explicitly set, this follows
+    /// debug assertions
+    pub fn overflow_checks_enabled(&self) -> bool {
+        self.overflow_checks
+            .unwrap_or_else(|| self.debug_assertions_enabled())
+    }
+
+    /// Serialize the codegen options back into a list of `-C` values, without the
+    /// leading `-C`
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+
+        fn push<T: Display>(args: &mut Vec<String>, key: &str, value: &Option<T>) {
+            if let Some(value) = value {
+                args.push(format!("{key}={value}"));
+            }
+        }
+
+        fn push_bool(args: &mut Vec<String>, key: &str, value: Option<bool>) {
+            push(args, key, &value.map(|b| if b { "yes" } else { "no" }));
+        }
+
+        fn push_path(args: &mut Vec<String>, key: &str, value: &Option<PathBuf>) {
+            push(args, key, &value.as_ref().map(|p| p.display()));
+        }
+
+        fn push_all(args: &mut Vec<String>, key: &str, values: &[String]) {
+            values
+                .iter()
+                .for_each(|value| args.push(format!("{key}={value}")));
+        }
+
+        push(&mut args, "ar", &self.ar);
+        push(&mut args, "code-model", &self.code_model);
+        push(&mut args, "codegen-units", &self.codegen_units);
+        push(
+            &mut args,
+            "collapse-macro-debuginfo",
+            &self.collapse_macro_debuginfo,
+        );
+        push(&mut args, "control-flow-guard", &self.control_flow_guard);
+        push_bool(&mut args, "debug-assertions", self.debug_assertions);
+        push(&mut args, "debuginfo", &self.debuginfo);
+        push_bool(
+            &mut args,
+            "default-linker-libraries",
+            self.default_linker_libraries,
+        );
+        push_path(&mut args, "dlltool", &self.dlltool);
+        push_bool(&mut args, "embed-bitcode", self.embed_bitcode);
+        push(&mut args, "extra-filename", &self.extra_filename);
+        push_bool(&mut args, "force-frame-pointers", self.force_frame_pointers);
+        push_bool(&mut args, "force-unwind-tables", self.force_unwind_tables);
+        push_path(&mut args, "incremental", &self.incremental);
+        push(&mut args, "inline-threshold", &self.inline_threshold);
+        push_bool(&mut args, "instrument-coverage", self.instrument_coverage);
+        push_all(&mut args, "link-arg", &self.link_args);
+        push_bool(&mut args, "link-dead-code", self.link_dead_code);
+        push_bool(&mut args, "link-self-contained", self.link_self_contained);
+        push_path(&mut args, "linker", &self.linker);
+        push(&mut args, "linker-flavor", &self.linker_flavor);
+        push(&mut args, "linker-plugin-lto", &self.linker_plugin_lto);
+        push_all(&mut args, "llvm-args", &self.llvm_args);
+        push(&mut args, "lto", &self.lto);
+        push_all(&mut args, "metadata", &self.metadata);
+        push_bool(
+            &mut args,
+            "no-prepopulate-passes",
+            self.no_prepopulate_passes,
+        );
+        push_bool(&mut args, "no-redzone", self.no_redzone);
+        push_bool(&mut args, "no-stack-check", self.no_stack_check);
+        push_bool(&mut args, "no-vectorize-loops", self.no_vectorize_loops);
+        push_bool(&mut args, "no-vectorize-slp", self.no_vectorize_slp);
+        push(&mut args, "opt-level", &self.opt_level);
+        push_bool(&mut args, "overflow-checks", self.overflow_checks);
+        push(&mut args, "panic", &self.panic);
+        push_all(&mut args, "passes", &self.passes);
+        push_bool(&mut args, "prefer-dynamic", self.prefer_dynamic);
+        push_path(&mut args, "profile-generate", &self.profile_generate);
+        push_path(&mut args, "profile-use", &self.profile_use);
+        push(&mut args, "relocation-model", &self.relocation_model);
+        push(&mut args, "relro-level", &self.relro_level);
+        push_all(&mut args, "remark", &self.remark);
+        push_bool(&mut args, "rpath", self.rpath);
+        if !self.sanitizers.is_empty() {
+            let sanitizers: Vec<String> =
+                self.sanitizers.iter().map(Sanitizer::to_string).collect();
+            args.push(format!("sanitize={}", sanitizers.join(",")));
+        }
+        push_bool(&mut args, "save-temps", self.save_temps);
+        push_bool(&mut args, "soft-float", self.soft_float);
+        push(&mut args, "split-debuginfo", &self.split_debuginfo);
+        push(&mut args, "strip", &self.strip);
+        push(
+            &mut args,
+            "symbol-mangling-version",
+            &self.symbol_mangling_version,
+        );
+        push(&mut args, "target-cpu", &self.target_cpu);
+        for feature in self.target_features.iter() {
+            args.push(format!("target-feature={feature}"));
+        }
+        push_all(
+            &mut args,
+            "unsafe-allow-abi-mismatch",
+            &self.unsafe_allow_abi_mismatch,
+        );
+
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(options: &[&str]) -> Result<CodegenOptions> {
+        let mut codegen = CodegenOptions::default();
+        options.iter().try_for_each(|opt| codegen.apply(opt))?;
+
+        Ok(codegen)
+    }
+
+    #[test]
+    fn typed_values() {
+        let codegen = parse(&["opt-level=3", "debuginfo=2", "panic=abort", "lto"]).unwrap();
+
+        assert_eq!(codegen.opt_level, Some(OptLevel::Three));
+        assert_eq!(codegen.debuginfo, Some(DebugInfo::Full));
+        assert_eq!(codegen.panic, Some(PanicStrategy::Abort));
+        assert_eq!(codegen.lto, Some(Lto::Fat));
+    }
+
+    #[test]
+    fn underscores_and_booleans() {
+        let codegen = parse(&["debug_assertions", "overflow-checks=off"]).unwrap();
+
+        assert_eq!(codegen.debug_assertions, Some(true));
+        assert_eq!(codegen.overflow_checks, Some(false));
+    }
+
+    #[test]
+    fn accumulated_values() {
+        let codegen = parse(&[
+            "link-args=-a -b",
+            "link-arg=-c",
+            "target-feature=+avx2,-sse4.1",
+        ])
+        .unwrap();
+
+        assert_eq!(codegen.link_args, vec!["-a", "-b", "-c"]);
+        assert_eq!(
+            codegen.target_features,
+            vec![
+                TargetFeature {
+                    name: String::from("avx2"),
+                    enabled: true
+                },
+                TargetFeature {
+                    name: String::from("sse4.1"),
+                    enabled: false
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn implied_checks() {
+        let debug = parse(&[]).unwrap();
+        let release = parse(&["opt-level=3"]).unwrap();
+        let checked_release = parse(&["opt-level=3", "debug-assertions"]).unwrap();
+
+        assert!(debug.debug_assertions_enabled() && debug.overflow_checks_enabled());
+        assert!(!release.debug_assertions_enabled() && !release.overflow_checks_enabled());
+        assert!(checked_release.overflow_checks_enabled());
+    }
+
+    #[test]
+    fn invalid_options() {
+        assert!(parse(&["opt-level=4"]).is_err());
+        assert!(parse(&["opt-level"]).is_err());
+        assert!(parse(&["target-feature=avx2"]).is_err());
+        assert!(parse(&["not-an-option=1"]).is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let codegen = parse(&[
+            "opt-level=s",
+            "extra-filename=-abcdef",
+            "metadata=abcdef",
+            "relocation-model=pic",
+            "strip=symbols",
+            "rpath=n",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            parse(
+                &codegen
+                    .to_args()
+                    .iter()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+            )
+            .unwrap(),
+            codegen
+        );
+    }
+}
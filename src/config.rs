@@ -3,7 +3,7 @@
 //! [`Gccrs::dump_config()`] function. This corresponds to invoking gccrs with the
 //! `-frust-dump-target_options` argument.
 
-use super::{Error, Result};
+use super::{codegen_options::TargetFeature, rustc_args::Cfg, Error, Result};
 use std::cmp::Ordering;
 use std::fmt::{Display, Formatter, Result as FmtResult};
 
@@ -58,6 +58,18 @@ impl Display for DumpedOption {
     }
 }
 
+/// Configuration options given on the command line are displayed alongside the dumped
+/// target options: `--cfg unix` is a singular value, while `--cfg feature="std"`
+/// behaves like a target specific option
+impl From<&Cfg> for DumpedOption {
+    fn from(cfg: &Cfg) -> DumpedOption {
+        match &cfg.value {
+            Some(value) => DumpedOption::TargetSpecific(cfg.name.clone(), format!("{value:?}")),
+            None => DumpedOption::OsInfo(cfg.name.clone()),
+        }
+    }
+}
+
 impl PartialOrd for DumpedOption {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
@@ -108,17 +120,44 @@ impl GccrsConfig {
 
         Ok(GccrsConfig { options })
     }
+
+    /// Add configuration options given on the command line to the dumped ones
+    pub fn add_cfgs(&mut self, cfgs: &[Cfg]) {
+        self.options.extend(cfgs.iter().map(DumpedOption::from));
+
+        self.options.sort();
+        self.options.dedup();
+    }
+
+    /// Make the `target_feature` options match the features explicitly enabled or
+    /// disabled on the command line
+    pub fn set_target_features(&mut self, features: &[TargetFeature]) {
+        for feature in features.iter() {
+            let option = DumpedOption::TargetSpecific(
+                String::from("target_feature"),
+                format!("{:?}", feature.name),
+            );
+
+            self.options.retain(|opt| *opt != option);
+            if feature.enabled {
+                self.options.push(option);
+            }
+        }
+
+        self.options.sort();
+    }
 }
 
 impl Display for GccrsConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        self.options.iter().try_for_each(|opt| write!(f, "{opt}"))
+        self.options.iter().try_for_each(|opt| writeln!(f, "{opt}"))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::DumpedOption;
+    use super::{DumpedOption, GccrsConfig};
+    use crate::{codegen_options::TargetFeature, rustc_args::Cfg};
 
     // FIXME: Useful for tests but really ugly, keep it?
     macro_rules! s {
@@ -148,6 +187,19 @@ mod tests {
         assert!(DumpedOption::from_str("k: v0: v1").is_err())
     }
 
+    #[test]
+    fn cli_cfg() {
+        let cfg = Cfg {
+            name: s!("feature"),
+            value: Some(s!("std")),
+        };
+
+        assert_eq!(
+            DumpedOption::from(&cfg),
+            DumpedOption::from_str(r#"feature: "std""#).unwrap()
+        )
+    }
+
     #[test]
     fn sorting() {
         let c0 = DumpedOption::from_str(r#"target_os="linux""#).unwrap();
@@ -160,4 +212,28 @@ mod tests {
 
         assert_eq!(v, vec![c0, c1, c2, c3]);
     }
+
+    #[test]
+    fn target_features() {
+        let dump = "target_feature: \"sse\"\ntarget_feature: \"sse2\"\nunix";
+        let mut config = GccrsConfig {
+            options: GccrsConfig::parse(s!(dump)).unwrap(),
+        };
+
+        config.set_target_features(&[
+            TargetFeature {
+                name: s!("sse2"),
+                enabled: false,
+            },
+            TargetFeature {
+                name: s!("crt-static"),
+                enabled: true,
+            },
+        ]);
+
+        assert_eq!(
+            config.to_string(),
+            "target_feature=\"crt-static\"\ntarget_feature=\"sse\"\nunix\n"
+        );
+    }
 }
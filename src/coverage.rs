@@ -0,0 +1,233 @@
+//! Collect the coverage data of programs compiled with `-C instrument-coverage`. `gccrs`
+//! instruments them for `gcov`, which writes a `.gcda` file next to the `.gcno` notes of
+//! each crate when the program exits. `gcov --json-format` turns these files into JSON
+//! describing the execution count of every line and function, which is merged here and
+//! exported in the `lcov` tracefile format understood by most coverage tools.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::{Error, Result};
+
+#[derive(Debug, Deserialize)]
+struct GcovLine {
+    line_number: u32,
+    count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct GcovFunction {
+    name: String,
+    demangled_name: Option<String>,
+    start_line: u32,
+    execution_count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct GcovFile {
+    file: String,
+    #[serde(default)]
+    lines: Vec<GcovLine>,
+    #[serde(default)]
+    functions: Vec<GcovFunction>,
+}
+
+/// Output of `gcov --json-format` for a single data file
+#[derive(Debug, Deserialize)]
+struct GcovOutput {
+    current_working_directory: String,
+    files: Vec<GcovFile>,
+}
+
+/// Execution counts collected for a source file
+#[derive(Debug, Default, PartialEq, Eq)]
+struct FileCoverage {
+    lines: BTreeMap<u32, u64>,
+    /// Functions, with their first line and execution count
+    functions: BTreeMap<String, (u32, u64)>,
+}
+
+impl FileCoverage {
+    fn covered_lines(&self) -> usize {
+        self.lines.values().filter(|count| **count > 0).count()
+    }
+
+    fn covered_functions(&self) -> usize {
+        self.functions
+            .values()
+            .filter(|(_, count)| *count > 0)
+            .count()
+    }
+}
+
+/// Coverage of a project, merged from the data of each of its crates
+#[derive(Debug, Default)]
+pub struct CoverageReport {
+    files: BTreeMap<PathBuf, FileCoverage>,
+}
+
+impl CoverageReport {
+    /// Add the output of `gcov --json-format --stdout` to the report. Sources included
+    /// in multiple crates, such as generic code, have their counts summed
+    pub fn add_gcov_json(&mut self, json: &str) -> Result {
+        // `gcov` outputs one JSON document per line when given multiple data files
+        for line in json.lines().filter(|line| !line.trim().is_empty()) {
+            let output: GcovOutput =
+                serde_json::from_str(line).map_err(|e| Error::InvalidCoverage(e.to_string()))?;
+            let cwd = Path::new(&output.current_working_directory);
+
+            for file in output.files {
+                let coverage = self.files.entry(cwd.join(&file.file)).or_default();
+
+                for line in file.lines {
+                    *coverage.lines.entry(line.line_number).or_default() += line.count;
+                }
+                for function in file.functions {
+                    let name = function.demangled_name.unwrap_or(function.name);
+                    let entry = coverage
+                        .functions
+                        .entry(name)
+                        .or_insert((function.start_line, 0));
+                    entry.1 += function.execution_count;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Only keep the source files inside of a directory, usually the workspace, in order
+    /// to leave out the standard library and dependencies from the registry
+    pub fn retain_under(&mut self, root: &Path) {
+        self.files.retain(|path, _| path.starts_with(root));
+    }
+
+    /// Export the report as an `lcov` tracefile
+    pub fn to_lcov(&self) -> String {
+        let mut lcov = String::from("TN:\n");
+
+        // Writing to a `String` cannot fail
+        for (path, coverage) in self.files.iter() {
+            let _ = writeln!(lcov, "SF:{}", path.display());
+            for (name, (line, _)) in coverage.functions.iter() {
+                let _ = writeln!(lcov, "FN:{line},{name}");
+            }
+            for (name, (_, count)) in coverage.functions.iter() {
+                let _ = writeln!(lcov, "FNDA:{count},{name}");
+            }
+            let _ = writeln!(lcov, "FNF:{}", coverage.functions.len());
+            let _ = writeln!(lcov, "FNH:{}", coverage.covered_functions());
+            for (line, count) in coverage.lines.iter() {
+                let _ = writeln!(lcov, "DA:{line},{count}");
+            }
+            let _ = writeln!(lcov, "LF:{}", coverage.lines.len());
+            let _ = writeln!(lcov, "LH:{}", coverage.covered_lines());
+            lcov.push_str("end_of_record\n");
+        }
+
+        lcov
+    }
+
+    /// Summarize the line coverage of each file in a table. Paths are displayed
+    /// relative to the given directory
+    pub fn summary(&self, root: &Path) -> String {
+        let rows: Vec<(String, usize, usize)> = self
+            .files
+            .iter()
+            .map(|(path, coverage)| {
+                (
+                    path.strip_prefix(root)
+                        .unwrap_or(path)
+                        .display()
+                        .to_string(),
+                    coverage.lines.len(),
+                    coverage.covered_lines(),
+                )
+            })
+            .collect();
+        let total = rows.iter().fold((0, 0), |(lines, covered), row| {
+            (lines + row.1, covered + row.2)
+        });
+
+        let width = rows
+            .iter()
+            .map(|(name, _, _)| name.len())
+            .chain(std::iter::once("Filename".len()))
+            .max()
+            .unwrap_or_default();
+        let percent = |lines: usize, covered: usize| match lines {
+            0 => String::from("-"),
+            _ => format!("{:.2}%", covered as f64 * 100.0 / lines as f64),
+        };
+
+        let mut summary = format!(
+            "{:width$}  {:>8}  {:>8}  {:>8}\n",
+            "Filename", "Lines", "Covered", "Cover"
+        );
+        for (name, lines, covered) in rows.iter() {
+            let _ = writeln!(
+                summary,
+                "{name:width$}  {lines:>8}  {covered:>8}  {:>8}",
+                percent(*lines, *covered)
+            );
+        }
+        let _ = writeln!(
+            summary,
+            "{:width$}  {:>8}  {:>8}  {:>8}",
+            "TOTAL",
+            total.0,
+            total.1,
+            percent(total.0, total.1)
+        );
+
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GCOV_OUTPUT: &str = r#"{"gcc_version": "13.2.0", "files": [{"file": "src/lib.rs", "functions": [{"blocks": 2, "end_column": 1, "start_line": 1, "name": "_ZN3foo3add17h0E", "blocks_executed": 2, "execution_count": 3, "demangled_name": "foo::add", "start_column": 1, "end_line": 3}, {"blocks": 2, "end_column": 1, "start_line": 5, "name": "_ZN3foo3sub17h0E", "blocks_executed": 0, "execution_count": 0, "demangled_name": "foo::sub", "start_column": 1, "end_line": 7}], "lines": [{"line_number": 1, "function_name": "_ZN3foo3add17h0E", "count": 3, "unexecuted_block": false, "branches": []}, {"line_number": 2, "function_name": "_ZN3foo3add17h0E", "count": 3, "unexecuted_block": false, "branches": []}, {"line_number": 5, "function_name": "_ZN3foo3sub17h0E", "count": 0, "unexecuted_block": true, "branches": []}, {"line_number": 6, "function_name": "_ZN3foo3sub17h0E", "count": 0, "unexecuted_block": true, "branches": []}]}, {"file": "/rustc/library/core/src/ops.rs", "functions": [], "lines": [{"line_number": 10, "count": 1, "unexecuted_block": false, "branches": []}]}], "format_version": "1", "current_working_directory": "/work/foo", "data_file": "target/debug/deps/foo-1234.gcda"}"#;
+
+    #[test]
+    fn gcov_to_lcov() {
+        let mut report = CoverageReport::default();
+        report.add_gcov_json(GCOV_OUTPUT).unwrap();
+        report.add_gcov_json(GCOV_OUTPUT).unwrap();
+        report.retain_under(Path::new("/work/foo"));
+
+        assert_eq!(
+            report.to_lcov(),
+            "TN:\n\
+             SF:/work/foo/src/lib.rs\n\
+             FN:1,foo::add\n\
+             FN:5,foo::sub\n\
+             FNDA:6,foo::add\n\
+             FNDA:0,foo::sub\n\
+             FNF:2\n\
+             FNH:1\n\
+             DA:1,6\n\
+             DA:2,6\n\
+             DA:5,0\n\
+             DA:6,0\n\
+             LF:4\n\
+             LH:2\n\
+             end_of_record\n"
+        );
+
+        let summary = report.summary(Path::new("/work/foo"));
+        assert!(summary.contains("src/lib.rs         4         2    50.00%"));
+        assert!(summary.contains("TOTAL              4         2    50.00%"));
+    }
+
+    #[test]
+    fn invalid_gcov_output() {
+        assert!(CoverageReport::default()
+            .add_gcov_json("gcov: cannot open data file")
+            .is_err());
+    }
+}
@@ -0,0 +1,623 @@
+//! Produce the dependency information requested with `--emit dep-info`. `cargo` reads
+//! these make-style files to know which source files and environment variables a crate
+//! depends on, and rebuilds the crate when one of them changes.
+//!
+//! `gccrs` is given GCC's `-MD` option, and writes the files the crate was compiled from
+//! as make rules, whose targets are replaced by the outputs `cargo` expects. These rules
+//! do not mention environment variables, so the listed sources are scanned for `env!`
+//! and `option_env!`.
+//!
+//! When `gccrs` does not write these rules, the crate is scanned starting from its root
+//! instead: Every out-of-line `mod` declaration is followed, and the files used by
+//! `include!`, `include_str!` and `include_bytes!` are collected along with the
+//! environment variables. Modules declared through macros are missed by this scan.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use super::Result;
+
+/// Tokens of Rust source code which are relevant when looking for dependencies
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Punct(char),
+}
+
+/// Very small lexer, which only needs to be precise enough to tell identifiers and
+/// string literals apart from comments and other literals
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Lexer {
+    fn tokenize(source: &str) -> Vec<Token> {
+        let mut lexer = Lexer {
+            chars: source.chars().collect(),
+            pos: 0,
+        };
+        let mut tokens = Vec::new();
+
+        while let Some(token) = lexer.next_token() {
+            tokens.push(token);
+        }
+
+        tokens
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn eat_while(&mut self, predicate: impl Fn(char) -> bool) -> String {
+        let start = self.pos;
+        while self.peek(0).is_some_and(&predicate) {
+            self.pos += 1;
+        }
+
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn skip_block_comment(&mut self) {
+        let mut depth = 0;
+
+        while let Some(c) = self.peek(0) {
+            match (c, self.peek(1)) {
+                ('/', Some('*')) => {
+                    depth += 1;
+                    self.pos += 2;
+                }
+                ('*', Some('/')) => {
+                    depth -= 1;
+                    self.pos += 2;
+
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    /// Read a string literal, starting after its opening quote
+    fn cooked_string(&mut self) -> String {
+        let mut value = String::new();
+
+        while let Some(c) = self.peek(0) {
+            self.pos += 1;
+
+            match c {
+                '"' => break,
+                '\\' => {
+                    let escaped = self.peek(0).unwrap_or_default();
+                    self.pos += 1;
+
+                    match escaped {
+                        'n' => value.push('\n'),
+                        't' => value.push('\t'),
+                        'r' => value.push('\r'),
+                        '0' => value.push('\0'),
+                        // Line continuations skip the following whitespace
+                        '\n' => {
+                            self.eat_while(char::is_whitespace);
+                        }
+                        'x' => {
+                            let code = self.eat_while(|c| c.is_ascii_hexdigit());
+                            value.extend(u8::from_str_radix(&code, 16).map(char::from));
+                        }
+                        'u' => {
+                            let code = self.eat_while(|c| c != '}');
+                            self.pos += 1;
+                            value.extend(
+                                u32::from_str_radix(code.trim_start_matches('{'), 16)
+                                    .ok()
+                                    .and_then(char::from_u32),
+                            );
+                        }
+                        c => value.push(c),
+                    }
+                }
+                c => value.push(c),
+            }
+        }
+
+        value
+    }
+
+    /// Read a raw string literal, starting after its `r` prefix
+    fn raw_string(&mut self) -> String {
+        let hashes = self.eat_while(|c| c == '#').len();
+        self.pos += 1;
+
+        let start = self.pos;
+        while self.pos < self.chars.len() {
+            let closing = self.peek(0) == Some('"')
+                && (1..=hashes).all(|offset| self.peek(offset) == Some('#'));
+
+            if closing {
+                let value = self.chars[start..self.pos].iter().collect();
+                self.pos += hashes + 1;
+
+                return value;
+            }
+
+            self.pos += 1;
+        }
+
+        self.chars[start..].iter().collect()
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        loop {
+            let c = self.peek(0)?;
+
+            match (c, self.peek(1)) {
+                (c, _) if c.is_whitespace() => self.pos += 1,
+                ('/', Some('/')) => {
+                    self.eat_while(|c| c != '\n');
+                }
+                ('/', Some('*')) => self.skip_block_comment(),
+                ('"', _) => {
+                    self.pos += 1;
+                    return Some(Token::Str(self.cooked_string()));
+                }
+                // Character literals, as opposed to lifetimes
+                ('\'', Some('\\')) => {
+                    self.pos += 3;
+                    self.eat_while(|c| c != '\'');
+                    self.pos += 1;
+                }
+                ('\'', Some(_)) if self.peek(2) == Some('\'') => self.pos += 3,
+                (c, _) if c.is_ascii_digit() => {
+                    self.eat_while(|c| c.is_alphanumeric() || c == '_');
+                }
+                (c, _) if c.is_alphabetic() || c == '_' => {
+                    let ident = self.eat_while(|c| c.is_alphanumeric() || c == '_');
+
+                    return Some(match (ident.as_str(), self.peek(0), self.peek(1)) {
+                        ("b" | "c", Some('"'), _) => {
+                            self.pos += 1;
+                            Token::Str(self.cooked_string())
+                        }
+                        ("r" | "br" | "cr", Some('"'), _) => Token::Str(self.raw_string()),
+                        ("r" | "br" | "cr", Some('#'), Some('#' | '"')) => {
+                            Token::Str(self.raw_string())
+                        }
+                        // Raw identifiers
+                        ("r", Some('#'), _) => {
+                            self.pos += 1;
+                            Token::Ident(self.eat_while(|c| c.is_alphanumeric() || c == '_'))
+                        }
+                        _ => Token::Ident(ident),
+                    });
+                }
+                (c, _) => {
+                    self.pos += 1;
+                    return Some(Token::Punct(c));
+                }
+            }
+        }
+    }
+}
+
+/// Files and environment variables a crate depends on
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DepInfo {
+    files: Vec<PathBuf>,
+    env: BTreeMap<String, Option<String>>,
+}
+
+/// An inline module, such as `mod foo { ... }`
+struct InlineModule {
+    /// Brace depth at which the module was opened
+    depth: usize,
+    dir: PathBuf,
+}
+
+impl DepInfo {
+    /// Collect the dependencies of a crate, starting from its root file
+    pub fn scan(root: &Path) -> Result<DepInfo> {
+        let mut dep_info = DepInfo::default();
+        let mut visited = HashSet::new();
+
+        dep_info.scan_file(
+            root,
+            root.parent().unwrap_or_else(|| Path::new("")),
+            &mut visited,
+        )?;
+
+        Ok(dep_info)
+    }
+
+    /// Collect the dependencies of a crate from the make rules written by `gccrs -MD`
+    pub fn from_makefile(makefile: &str) -> Result<DepInfo> {
+        let mut dep_info = DepInfo::default();
+
+        for rule in makefile.replace("\\\n", " ").lines() {
+            // Targets are separated from their prerequisites by a colon followed by
+            // whitespace, as paths may contain colons
+            let prerequisites = match rule.find(": ") {
+                Some(pos) => &rule[pos + 2..],
+                None => continue,
+            };
+
+            for file in DepInfo::split_prerequisites(prerequisites) {
+                dep_info.add_file(PathBuf::from(file));
+            }
+        }
+
+        for file in dep_info.files.clone().iter() {
+            if file.extension().is_some_and(|ext| ext == "rs") {
+                dep_info.scan_env_vars(file)?;
+            }
+        }
+
+        Ok(dep_info)
+    }
+
+    /// Split a list of make prerequisites on whitespace, except when escaped
+    fn split_prerequisites(prerequisites: &str) -> Vec<String> {
+        let mut files = vec![String::new()];
+        let mut chars = prerequisites.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match (c, chars.peek()) {
+                ('\\', Some(' ')) | ('$', Some('$')) => files
+                    .last_mut()
+                    .unwrap()
+                    .push(chars.next().unwrap_or_default()),
+                (c, _) if c.is_whitespace() => files.push(String::new()),
+                (c, _) => files.last_mut().unwrap().push(c),
+            }
+        }
+
+        files.retain(|file| !file.is_empty());
+
+        files
+    }
+
+    /// Collect the environment variables read by a source file
+    fn scan_env_vars(&mut self, file: &Path) -> Result {
+        let tokens = Lexer::tokenize(&std::fs::read_to_string(file)?);
+
+        for (i, token) in tokens.iter().enumerate() {
+            if let (
+                Token::Ident(mac),
+                [Token::Punct('!'), Token::Punct('(' | '[' | '{'), Token::Str(var), ..],
+            ) = (token, &tokens[i + 1..])
+            {
+                if mac == "env" || mac == "option_env" {
+                    self.env_var(var);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_file(&mut self, file: PathBuf) {
+        if !self.files.contains(&file) {
+            self.files.push(file);
+        }
+    }
+
+    /// Evaluate the string given to a macro such as `include_str!`, which may be built
+    /// using `concat!` and `env!`
+    fn string_expr(&mut self, tokens: &[Token]) -> Option<String> {
+        match tokens {
+            [Token::Str(s), ..] => Some(s.clone()),
+            [Token::Ident(mac), Token::Punct('!'), Token::Punct('('), rest @ ..]
+                if mac == "env" =>
+            {
+                match rest.first() {
+                    Some(Token::Str(var)) => self.env_var(var),
+                    _ => None,
+                }
+            }
+            [Token::Ident(mac), Token::Punct('!'), Token::Punct('('), rest @ ..]
+                if mac == "concat" =>
+            {
+                let mut value = String::new();
+                let mut depth = 0;
+
+                for (i, token) in rest.iter().enumerate() {
+                    match token {
+                        Token::Punct('(') => depth += 1,
+                        Token::Punct(')') if depth == 0 => return Some(value),
+                        Token::Punct(')') => depth -= 1,
+                        Token::Str(s) if depth == 0 => value.push_str(s),
+                        Token::Ident(_) if depth == 0 => {
+                            value.push_str(&self.string_expr(&rest[i..])?)
+                        }
+                        _ => {}
+                    }
+                }
+
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Remember that the crate reads an environment variable, and get its value
+    fn env_var(&mut self, var: &str) -> Option<String> {
+        let value = std::env::var(var).ok();
+        self.env.insert(var.to_owned(), value.clone());
+
+        value
+    }
+
+    /// Scan a source file. `module_dir` is the directory in which the files of its
+    /// child modules are located
+    fn scan_file(
+        &mut self,
+        file: &Path,
+        module_dir: &Path,
+        visited: &mut HashSet<PathBuf>,
+    ) -> Result {
+        if !visited.insert(file.to_owned()) {
+            return Ok(());
+        }
+
+        self.add_file(file.to_owned());
+
+        let source = std::fs::read_to_string(file)?;
+        let tokens = Lexer::tokenize(&source);
+        let file_dir = file.parent().unwrap_or_else(|| Path::new(""));
+
+        let mut inline_modules: Vec<InlineModule> = Vec::new();
+        let mut depth = 0;
+        let mut path_attr = None;
+
+        for (i, token) in tokens.iter().enumerate() {
+            let next = &tokens[i + 1..];
+
+            match (token, next) {
+                (
+                    Token::Punct('#'),
+                    [Token::Punct('['), Token::Ident(attr), Token::Punct('='), Token::Str(path), Token::Punct(']'), ..],
+                ) if attr == "path" => path_attr = Some(path.clone()),
+                (
+                    Token::Ident(keyword),
+                    [Token::Ident(name), Token::Punct(end @ (';' | '{')), ..],
+                ) if keyword == "mod" => {
+                    let current_dir = inline_modules
+                        .last()
+                        .map(|module| module.dir.as_path())
+                        .unwrap_or(module_dir);
+
+                    // Paths given using `#[path]` are relative to the current file,
+                    // unless the declaration is inside of an inline module
+                    let path_base = match inline_modules.is_empty() {
+                        true => file_dir,
+                        false => current_dir,
+                    };
+
+                    match (end, path_attr.take()) {
+                        (';', Some(path)) => {
+                            let path = path_base.join(path);
+                            let dir = path.parent().unwrap_or_else(|| Path::new("")).to_owned();
+
+                            self.scan_module(&path, &dir, visited)?;
+                        }
+                        (';', None) => {
+                            let dir = current_dir.join(name);
+                            let candidates =
+                                [current_dir.join(format!("{name}.rs")), dir.join("mod.rs")];
+
+                            if let Some(path) = candidates.iter().find(|path| path.is_file()) {
+                                self.scan_file(path, &dir, visited)?;
+                            }
+                        }
+                        (_, path) => inline_modules.push(InlineModule {
+                            depth: depth + 1,
+                            dir: match path {
+                                Some(path) => path_base.join(path),
+                                None => current_dir.join(name),
+                            },
+                        }),
+                    }
+                }
+                (
+                    Token::Ident(mac),
+                    [Token::Punct('!'), Token::Punct('(' | '[' | '{'), rest @ ..],
+                ) if mac == "include" || mac == "include_str" || mac == "include_bytes" => {
+                    if let Some(path) = self.string_expr(rest) {
+                        self.add_file(file_dir.join(path));
+                    }
+                }
+                (
+                    Token::Ident(mac),
+                    [Token::Punct('!'), Token::Punct('(' | '[' | '{'), Token::Str(var), ..],
+                ) if mac == "env" || mac == "option_env" => {
+                    self.env_var(var);
+                }
+                (Token::Punct('{'), _) => depth += 1,
+                (Token::Punct('}'), _) => {
+                    depth = depth.saturating_sub(1);
+                    inline_modules.retain(|module| module.depth <= depth);
+                }
+                (Token::Punct(';'), _) => path_attr = None,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Scan a module whose path was given explicitly, if it exists. Missing modules
+    /// are reported by `gccrs` itself
+    fn scan_module(&mut self, path: &Path, dir: &Path, visited: &mut HashSet<PathBuf>) -> Result {
+        match path.is_file() {
+            true => self.scan_file(path, dir, visited),
+            false => Ok(()),
+        }
+    }
+
+    /// Format the dependency information like `rustc` does, with one rule per output
+    pub fn to_makefile(&self, targets: &[PathBuf]) -> String {
+        let escape_path = |path: &Path| path.display().to_string().replace(' ', "\\ ");
+        let files: Vec<String> = self.files.iter().map(|file| escape_path(file)).collect();
+
+        let mut makefile = String::new();
+        for target in targets.iter() {
+            makefile.push_str(&format!("{}: {}\n\n", escape_path(target), files.join(" ")));
+        }
+        for file in files.iter() {
+            makefile.push_str(&format!("{file}:\n"));
+        }
+
+        if !self.env.is_empty() {
+            makefile.push('\n');
+        }
+        for (var, value) in self.env.iter() {
+            match value {
+                Some(value) => makefile.push_str(&format!(
+                    "# env-dep:{var}={}\n",
+                    value
+                        .replace('\\', "\\\\")
+                        .replace('\n', "\\n")
+                        .replace('\r', "\\r")
+                )),
+                None => makefile.push_str(&format!("# env-dep:{var}\n")),
+            }
+        }
+
+        makefile
+    }
+
+    /// Write the dependency information to a file
+    pub fn write(&self, path: &Path, targets: &[PathBuf]) -> Result {
+        std::fs::write(path, self.to_makefile(targets))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens() {
+        let tokens = Lexer::tokenize(
+            r####"
+            // mod commented;
+            /* mod /* nested */ commented; */
+            let c = '"'; let q = '\''; let s = "mod \"quoted\";"; let r = r#"a"b"#;
+            fn f<'a>(x: &'a str) -> r#mod {}
+            "####,
+        );
+
+        assert!(!tokens.contains(&Token::Ident(String::from("commented"))));
+        assert!(tokens.contains(&Token::Str(String::from("mod \"quoted\";"))));
+        assert!(tokens.contains(&Token::Str(String::from("a\"b"))));
+        assert!(tokens.contains(&Token::Ident(String::from("mod"))));
+        assert!(tokens.contains(&Token::Ident(String::from("str"))));
+    }
+
+    #[test]
+    fn crate_dependencies() {
+        let dir = tempdir::TempDir::new("dep-info").unwrap();
+        let src = dir.path().join("src");
+        let write = |path: &str, content: &str| {
+            let path = src.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+
+        write(
+            "lib.rs",
+            r#"
+            mod a;
+            pub mod b;
+            #[path = "other/c.rs"]
+            mod c;
+            mod inline {
+                mod d;
+            }
+            mod missing;
+            const DATA: &str = include_str!("data.txt");
+            const VAR: &str = env!("CARGO_PKG_NAME");
+            "#,
+        );
+        write("a.rs", "mod nested;");
+        write("a/nested.rs", "");
+        write("b/mod.rs", "");
+        write("other/c.rs", "");
+        write("inline/d.rs", "");
+        write("data.txt", "");
+
+        let dep_info = DepInfo::scan(&src.join("lib.rs")).unwrap();
+
+        let files: Vec<PathBuf> = [
+            "lib.rs",
+            "a.rs",
+            "a/nested.rs",
+            "b/mod.rs",
+            "other/c.rs",
+            "inline/d.rs",
+            "data.txt",
+        ]
+        .iter()
+        .map(|file| src.join(file))
+        .collect();
+
+        assert_eq!(dep_info.files, files);
+        // `cargo test` sets the variables describing the package when running tests
+        assert_eq!(
+            dep_info.env.get("CARGO_PKG_NAME"),
+            Some(&Some(String::from("cargo-gccrs")))
+        );
+    }
+
+    #[test]
+    fn gcc_dependencies() {
+        let dir = tempdir::TempDir::new("dep-info").unwrap();
+        let (lib, generated) = (dir.path().join("lib.rs"), dir.path().join("my gen.rs"));
+        std::fs::write(&lib, "const VAR: &str = env!(\"CARGO_PKG_NAME\");").unwrap();
+        std::fs::write(&generated, "").unwrap();
+
+        let makefile = format!(
+            "out/foo.o: {} \\\n {}\n\n{}:\n",
+            lib.display(),
+            generated.display().to_string().replace(' ', "\\ "),
+            lib.display()
+        );
+
+        let dep_info = DepInfo::from_makefile(&makefile).unwrap();
+
+        assert_eq!(dep_info.files, vec![lib, generated]);
+        assert_eq!(
+            dep_info.env.get("CARGO_PKG_NAME"),
+            Some(&Some(String::from("cargo-gccrs")))
+        );
+    }
+
+    #[test]
+    fn makefile() {
+        let dep_info = DepInfo {
+            files: vec![
+                PathBuf::from("src/main.rs"),
+                PathBuf::from("src/my file.rs"),
+            ],
+            env: BTreeMap::from([
+                (String::from("FOO"), Some(String::from("a\nb"))),
+                (String::from("BAR"), None),
+            ]),
+        };
+
+        assert_eq!(
+            dep_info.to_makefile(&[PathBuf::from("out/main.d")]),
+            "out/main.d: src/main.rs src/my\\ file.rs\n\n\
+             src/main.rs:\n\
+             src/my\\ file.rs:\n\
+             \n\
+             # env-dep:BAR\n\
+             # env-dep:FOO=a\\nb\n"
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//! Handle dependencies between crates compiled with `gccrs`. `cargo` gives each crate
+//! its direct dependencies using `--extern`, and lets `rustc` find the rest in the
+//! `-L dependency=` search paths using the metadata stored inside each rlib.
+//!
+//! `gccrs` rlibs are plain archives of object files, and the crate metadata is exported
+//! next to them in a `.rox` file. Since the final link of a binary or shared library
+//! needs every archive in the dependency graph, each rlib is also accompanied by a
+//! `.deps` file listing the archives it depends on, the native libraries which were not
+//! bundled inside of it, and the panic strategy it was compiled with.
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::path::{Path, PathBuf};
+
+use super::{codegen_options::PanicStrategy, rustc_args::ExternCrate, Error, Result};
+
+/// Get the path of the crate metadata exported by `gccrs` for a given library
+pub fn metadata_file(library: &Path) -> PathBuf {
+    library.with_extension("rox")
+}
+
+/// An external crate, resolved to the files `gccrs` needs to use it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub name: String,
+    /// Crate metadata, given to the `gccrs` frontend
+    pub metadata: PathBuf,
+    /// Archive or shared object to give to the linker
+    pub link_input: PathBuf,
+}
+
+impl TryFrom<&ExternCrate> for Dependency {
+    type Error = Error;
+
+    fn try_from(extern_crate: &ExternCrate) -> Result<Dependency> {
+        let path = extern_crate.path.as_ref().ok_or_else(|| {
+            Error::InvalidArg(format!(
+                "gccrs needs the path of external crate `{}`",
+                extern_crate.name
+            ))
+        })?;
+
+        let (metadata, link_input) = match path.extension().and_then(|ext| ext.to_str()) {
+            // When pipelining, `cargo` gives the path to the `.rmeta` file of a
+            // library. The archive is always produced next to it
+            Some("rlib") | Some("rmeta") => (metadata_file(path), path.with_extension("rlib")),
+            // Shared objects are given as is to both the frontend and the linker
+            _ => (path.clone(), path.clone()),
+        };
+
+        Ok(Dependency {
+            name: extern_crate.name.clone(),
+            metadata,
+            link_input,
+        })
+    }
+}
+
+/// Link information stored next to every rlib produced by `gccrs`
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct LinkInfo {
+    /// Archives of the direct dependencies of the library
+    archives: Vec<PathBuf>,
+    /// Linker arguments required by the native libraries the library uses
+    native_args: Vec<String>,
+    /// Panic strategy the library was compiled with
+    panic: Option<PanicStrategy>,
+}
+
+impl LinkInfo {
+    /// Create the link information of a library from its direct dependencies, native
+    /// linker arguments and panic strategy
+    pub fn new(
+        dependencies: &[Dependency],
+        native_args: &[String],
+        panic: Option<PanicStrategy>,
+    ) -> LinkInfo {
+        let archives = dependencies
+            .iter()
+            .map(|dep| &dep.link_input)
+            .filter(|input| input.extension().is_some_and(|ext| ext == "rlib"))
+            .cloned()
+            .collect();
+
+        LinkInfo {
+            archives,
+            native_args: Vec::from(native_args),
+            panic,
+        }
+    }
+
+    fn path(library: &Path) -> PathBuf {
+        library.with_extension("deps")
+    }
+
+    fn parse(input: &str) -> Result<LinkInfo> {
+        let mut info = LinkInfo::default();
+
+        for line in input.lines().filter(|line| !line.is_empty()) {
+            match line.split_once(' ') {
+                Some(("archive", path)) => info.archives.push(PathBuf::from(path)),
+                Some(("native", arg)) => info.native_args.push(arg.to_owned()),
+                Some(("panic", strategy)) => {
+                    info.panic = Some(
+                        PanicStrategy::try_from(strategy)
+                            .map_err(|_| Error::InvalidLinkInfo(line.to_owned()))?,
+                    )
+                }
+                _ => return Err(Error::InvalidLinkInfo(line.to_owned())),
+            }
+        }
+
+        Ok(info)
+    }
+
+    /// Read the link information of a library. Libraries which were not compiled by
+    /// `gccrs` have no dependencies
+    pub fn read(library: &Path) -> Result<LinkInfo> {
+        match std::fs::read_to_string(LinkInfo::path(library)) {
+            Ok(content) => LinkInfo::parse(&content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(LinkInfo::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Store the link information next to a library. The file is written under a
+    /// temporary name first, and renamed once complete so that it is never read partially
+    pub fn write(&self, library: &Path) -> Result {
+        let path = LinkInfo::path(library);
+        let temporary = path.with_extension("deps.tmp");
+
+        std::fs::write(&temporary, self.to_string())?;
+        std::fs::rename(&temporary, &path)?;
+
+        Ok(())
+    }
+
+    fn visit(archive: &Path, visited: &mut HashSet<PathBuf>, order: &mut Vec<PathBuf>) -> Result {
+        if !visited.insert(archive.to_owned()) {
+            return Ok(());
+        }
+
+        for dependency in LinkInfo::read(archive)?.archives.iter() {
+            LinkInfo::visit(dependency, visited, order)?;
+        }
+
+        order.push(archive.to_owned());
+
+        Ok(())
+    }
+
+    /// Collect every archive needed to link a crate depending on the given libraries,
+    /// transitively. Archives are sorted so that each one comes before the archives it
+    /// depends on, which is the order expected by the linker. Shared objects come last
+    pub fn link_order(dependencies: &[Dependency]) -> Result<Vec<PathBuf>> {
+        let mut visited = HashSet::new();
+        let mut order = Vec::new();
+
+        for archive in LinkInfo::new(dependencies, &[], None).archives.iter() {
+            LinkInfo::visit(archive, &mut visited, &mut order)?;
+        }
+
+        order.reverse();
+
+        let shared_objects = dependencies
+            .iter()
+            .map(|dep| &dep.link_input)
+            .filter(|input| !visited.contains(*input));
+        order.extend(shared_objects.cloned());
+
+        Ok(order)
+    }
+
+    /// Check that libraries can be linked into a crate using a given panic strategy.
+    /// Like with `rustc`, libraries which unwind can be linked into a crate which
+    /// aborts, but libraries which abort cannot be linked into a crate which unwinds
+    pub fn check_panic_strategy(archives: &[PathBuf], panic: PanicStrategy) -> Result {
+        for archive in archives.iter() {
+            if let (Some(PanicStrategy::Abort), PanicStrategy::Unwind) =
+                (LinkInfo::read(archive)?.panic, panic)
+            {
+                return Err(Error::PanicStrategy(
+                    archive.display().to_string(),
+                    PanicStrategy::Abort.to_string(),
+                    panic.to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Collect the native linker arguments required by a list of archives, in order
+    pub fn native_args(archives: &[PathBuf]) -> Result<Vec<String>> {
+        let mut args = Vec::new();
+
+        for archive in archives.iter() {
+            args.append(&mut LinkInfo::read(archive)?.native_args);
+        }
+
+        Ok(args)
+    }
+}
+
+impl Display for LinkInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.archives
+            .iter()
+            .try_for_each(|archive| writeln!(f, "archive {}", archive.display()))?;
+        self.native_args
+            .iter()
+            .try_for_each(|arg| writeln!(f, "native {arg}"))?;
+
+        match self.panic {
+            Some(panic) => writeln!(f, "panic {panic}"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dependency(name: &str, dir: &Path) -> Dependency {
+        Dependency::try_from(&ExternCrate {
+            name: name.to_owned(),
+            path: Some(dir.join(format!("lib{name}.rlib"))),
+            options: vec![],
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn extern_paths() {
+        let rmeta = Dependency::try_from(&ExternCrate {
+            name: String::from("foo"),
+            path: Some(PathBuf::from("deps/libfoo-1234.rmeta")),
+            options: vec![],
+        })
+        .unwrap();
+
+        assert_eq!(rmeta.metadata, PathBuf::from("deps/libfoo-1234.rox"));
+        assert_eq!(rmeta.link_input, PathBuf::from("deps/libfoo-1234.rlib"));
+    }
+
+    #[test]
+    fn transitive_link_order() {
+        let dir = tempdir::TempDir::new("link-info").unwrap();
+        let (a, b, c) = (
+            dependency("a", dir.path()),
+            dependency("b", dir.path()),
+            dependency("c", dir.path()),
+        );
+
+        // a -> b -> c, and a -> c
+        LinkInfo::new(&[b.clone(), c.clone()], &[String::from("-lfoo")], None)
+            .write(&a.link_input)
+            .unwrap();
+        LinkInfo::new(
+            std::slice::from_ref(&c),
+            &[String::from("-lbar")],
+            Some(PanicStrategy::Abort),
+        )
+        .write(&b.link_input)
+        .unwrap();
+
+        let order = LinkInfo::link_order(&[c.clone(), a.clone()]).unwrap();
+
+        assert_eq!(
+            order,
+            vec![a.link_input.clone(), b.link_input.clone(), c.link_input]
+        );
+        assert_eq!(
+            LinkInfo::native_args(&order).unwrap(),
+            vec!["-lfoo", "-lbar"]
+        );
+        assert!(LinkInfo::check_panic_strategy(&order, PanicStrategy::Abort).is_ok());
+        assert!(LinkInfo::check_panic_strategy(&order, PanicStrategy::Unwind).is_err());
+    }
+
+    #[test]
+    fn invalid_link_info() {
+        assert!(LinkInfo::parse("archive libfoo.rlib\nnative -lfoo\n").is_ok());
+        assert!(LinkInfo::parse("libfoo.rlib").is_err());
+        assert!(LinkInfo::parse("panic crash").is_err());
+    }
+}
@@ -0,0 +1,656 @@
+//! Convert the diagnostics emitted by `gccrs` into `rustc`'s JSON format. When given
+//! `--error-format=json`, `rustc` outputs one JSON object per diagnostic on stderr,
+//! which `cargo`, IDEs and other tools parse. `gccrs` is instead run with GCC's
+//! `-fdiagnostics-format=json`, which outputs a single JSON array describing every
+//! diagnostic once compilation is over.
+//!
+//! The same stream also carries artifact notifications, which tell `cargo` when each
+//! output has been written.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    lints,
+    rustc_args::{EmitKind, JsonOption},
+};
+
+/// Position in a source file, as given by GCC. Lines and columns start at one
+#[derive(Debug, Clone, Deserialize)]
+struct GccPosition {
+    file: String,
+    line: usize,
+    column: usize,
+    #[serde(rename = "byte-column")]
+    byte_column: Option<usize>,
+}
+
+impl GccPosition {
+    fn byte_column(&self) -> usize {
+        self.byte_column.unwrap_or(self.column)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GccLocation {
+    caret: GccPosition,
+    start: Option<GccPosition>,
+    /// Last character of the location, included in it
+    finish: Option<GccPosition>,
+    label: Option<String>,
+}
+
+/// Replacement suggested by GCC. `next` is the position right after the replaced text
+#[derive(Debug, Clone, Deserialize)]
+struct GccFixit {
+    start: GccPosition,
+    next: GccPosition,
+    string: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct GccRule {
+    id: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct GccMetadata {
+    #[serde(default)]
+    rules: Vec<GccRule>,
+}
+
+/// A diagnostic, as output by `gcc -fdiagnostics-format=json`
+#[derive(Debug, Clone, Deserialize)]
+struct GccDiagnostic {
+    kind: String,
+    message: String,
+    #[serde(default)]
+    children: Vec<GccDiagnostic>,
+    #[serde(default)]
+    locations: Vec<GccLocation>,
+    #[serde(default)]
+    fixits: Vec<GccFixit>,
+    /// Warning option controlling the diagnostic, such as `-Wunused-variable`
+    option: Option<String>,
+    metadata: Option<GccMetadata>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiagnosticCode {
+    code: String,
+    explanation: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiagnosticSpanLine {
+    text: String,
+    highlight_start: usize,
+    highlight_end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiagnosticSpan {
+    file_name: String,
+    byte_start: usize,
+    byte_end: usize,
+    line_start: usize,
+    line_end: usize,
+    column_start: usize,
+    column_end: usize,
+    is_primary: bool,
+    text: Vec<DiagnosticSpanLine>,
+    label: Option<String>,
+    suggested_replacement: Option<String>,
+    suggestion_applicability: Option<String>,
+    expansion: Option<()>,
+}
+
+/// A diagnostic, following the schema of `rustc --error-format=json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    #[serde(rename = "$message_type")]
+    message_type: &'static str,
+    message: String,
+    code: Option<DiagnosticCode>,
+    level: String,
+    spans: Vec<DiagnosticSpan>,
+    children: Vec<Diagnostic>,
+    rendered: Option<String>,
+}
+
+/// Notification sent when an output is written, if requested using `--json=artifacts`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ArtifactNotification<'a> {
+    #[serde(rename = "$message_type")]
+    message_type: &'static str,
+    artifact: &'a Path,
+    emit: String,
+}
+
+impl ArtifactNotification<'_> {
+    pub fn new(artifact: &Path, emit: EmitKind) -> ArtifactNotification<'_> {
+        ArtifactNotification {
+            message_type: "artifact",
+            artifact,
+            emit: emit.to_string(),
+        }
+    }
+}
+
+/// Source files read to fill the text of the spans, split into lines. Lines keep
+/// their terminating newline so that byte offsets can be computed
+#[derive(Default)]
+struct SourceCache {
+    files: HashMap<String, Option<Vec<String>>>,
+}
+
+impl SourceCache {
+    fn lines(&mut self, file: &str) -> Option<&Vec<String>> {
+        self.files
+            .entry(file.to_owned())
+            .or_insert_with(|| {
+                std::fs::read_to_string(PathBuf::from(file))
+                    .ok()
+                    .map(|content| content.split_inclusive('\n').map(str::to_owned).collect())
+            })
+            .as_ref()
+    }
+
+    fn line(&mut self, file: &str, line: usize) -> Option<String> {
+        self.lines(file)?
+            .get(line.checked_sub(1)?)
+            .map(|line| line.trim_end_matches(['\n', '\r']).to_owned())
+    }
+
+    /// Byte offset of a position in its file
+    fn byte_offset(&mut self, file: &str, line: usize, byte_column: usize) -> usize {
+        let line_start: usize = self
+            .lines(file)
+            .map(|lines| {
+                lines
+                    .iter()
+                    .take(line.saturating_sub(1))
+                    .map(String::len)
+                    .sum()
+            })
+            .unwrap_or_default();
+
+        line_start + byte_column.saturating_sub(1)
+    }
+
+    /// Convert a byte column into a character column
+    fn char_column(&mut self, file: &str, line: usize, byte_column: usize) -> usize {
+        match self.line(file, line) {
+            Some(text) => {
+                let prefix = text.as_bytes().get(..byte_column.saturating_sub(1));
+                let prefix = prefix.map(String::from_utf8_lossy).unwrap_or_default();
+
+                prefix.chars().count() + 1
+            }
+            None => byte_column,
+        }
+    }
+}
+
+impl DiagnosticSpan {
+    /// Create a span from its first position and the position following it
+    fn new(
+        sources: &mut SourceCache,
+        start: &GccPosition,
+        end: &GccPosition,
+        is_primary: bool,
+        label: Option<String>,
+    ) -> DiagnosticSpan {
+        let file = start.file.as_str();
+        let column_start = sources.char_column(file, start.line, start.byte_column());
+        let column_end = sources.char_column(file, end.line, end.byte_column());
+
+        let text = (start.line..=end.line)
+            .filter_map(|line| {
+                let text = sources.line(file, line)?;
+                let highlight_start = match line == start.line {
+                    true => column_start,
+                    false => 1,
+                };
+                let highlight_end = match line == end.line {
+                    true => column_end,
+                    false => text.chars().count() + 1,
+                };
+
+                Some(DiagnosticSpanLine {
+                    text,
+                    highlight_start,
+                    highlight_end,
+                })
+            })
+            .collect();
+
+        DiagnosticSpan {
+            file_name: start.file.clone(),
+            byte_start: sources.byte_offset(file, start.line, start.byte_column()),
+            byte_end: sources.byte_offset(file, end.line, end.byte_column()),
+            line_start: start.line,
+            line_end: end.line,
+            column_start,
+            column_end,
+            is_primary,
+            text,
+            label,
+            suggested_replacement: None,
+            suggestion_applicability: None,
+            expansion: None,
+        }
+    }
+
+    fn from_location(
+        sources: &mut SourceCache,
+        location: &GccLocation,
+        is_primary: bool,
+    ) -> DiagnosticSpan {
+        let start = location.start.as_ref().unwrap_or(&location.caret);
+
+        // GCC includes the last character in the location, `rustc` does not
+        let mut end = location.finish.clone().unwrap_or_else(|| start.clone());
+        end.byte_column = Some(end.byte_column() + 1);
+
+        DiagnosticSpan::new(sources, start, &end, is_primary, location.label.clone())
+    }
+}
+
+/// Convert a GCC diagnostic kind into a `rustc` level
+fn level(kind: &str) -> &'static str {
+    match kind {
+        "warning" | "pedwarn" | "anachronism" => "warning",
+        "note" => "note",
+        "help" => "help",
+        "internal compiler error" => "error: internal compiler error",
+        _ => "error",
+    }
+}
+
+impl Diagnostic {
+    /// Create a diagnostic which does not point to any source code
+    pub fn message(level: &str, message: &str) -> Diagnostic {
+        let mut diagnostic = Diagnostic {
+            message_type: "diagnostic",
+            message: message.to_owned(),
+            code: None,
+            level: level.to_owned(),
+            spans: vec![],
+            children: vec![],
+            rendered: None,
+        };
+        diagnostic.rendered = Some(diagnostic.render(&[]));
+
+        diagnostic
+    }
+
+    /// Get the diagnostic as rendered in `rustc`'s human readable output
+    pub fn rendered(&self) -> Option<&str> {
+        self.rendered.as_deref()
+    }
+
+    fn from_gcc(sources: &mut SourceCache, gcc: &GccDiagnostic) -> Diagnostic {
+        // `gccrs` error codes are given as rules, while warnings are controlled by an
+        // option similar to a lint, reported under the name of the matching `rustc` lint
+        let code = gcc
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.rules.first())
+            .map(|rule| rule.id.clone())
+            .or_else(|| {
+                gcc.option
+                    .as_ref()
+                    .and_then(|option| match lints::rustc_lint(option) {
+                        Some(lint) => Some(lint.to_owned()),
+                        None => option
+                            .strip_prefix("-Werror=")
+                            .or_else(|| option.strip_prefix("-W"))
+                            .map(|lint| lint.replace('-', "_")),
+                    })
+            })
+            .map(|code| DiagnosticCode {
+                code,
+                explanation: None,
+            });
+
+        let spans = gcc
+            .locations
+            .iter()
+            .enumerate()
+            .map(|(i, location)| DiagnosticSpan::from_location(sources, location, i == 0))
+            .collect();
+
+        let mut children: Vec<Diagnostic> = gcc
+            .children
+            .iter()
+            .map(|child| Diagnostic::from_gcc(sources, child))
+            .collect();
+
+        for fixit in gcc.fixits.iter() {
+            let mut span = DiagnosticSpan::new(sources, &fixit.start, &fixit.next, true, None);
+            span.suggested_replacement = Some(fixit.string.clone());
+            span.suggestion_applicability = Some(String::from("MaybeIncorrect"));
+
+            children.push(Diagnostic {
+                message_type: "diagnostic",
+                message: String::from("try this"),
+                code: None,
+                level: String::from("help"),
+                spans: vec![span],
+                children: vec![],
+                rendered: None,
+            });
+        }
+
+        Diagnostic {
+            message_type: "diagnostic",
+            message: gcc.message.clone(),
+            code,
+            level: level(&gcc.kind).to_owned(),
+            spans,
+            children,
+            rendered: None,
+        }
+    }
+
+    fn header(&self, ansi: bool) -> String {
+        let level = match &self.code {
+            // Lints are not displayed next to the level
+            Some(code) if code.code.starts_with('E') => format!("{}[{}]", self.level, code.code),
+            _ => self.level.clone(),
+        };
+
+        match ansi {
+            true => format!(
+                "{}{level}\x1b[0m\x1b[1m: {}\x1b[0m",
+                color(&self.level, ansi),
+                self.message
+            ),
+            false => format!("{level}: {}", self.message),
+        }
+    }
+
+    /// Render the diagnostic like `rustc`'s human readable output
+    fn render(&self, json: &[JsonOption]) -> String {
+        let ansi = json.contains(&JsonOption::DiagnosticRenderedAnsi);
+
+        if json.contains(&JsonOption::DiagnosticShort) {
+            return match self.spans.iter().find(|span| span.is_primary) {
+                Some(span) => format!(
+                    "{}:{}:{}: {}\n",
+                    span.file_name,
+                    span.line_start,
+                    span.column_start,
+                    self.header(ansi)
+                ),
+                None => format!("{}\n", self.header(ansi)),
+            };
+        }
+
+        let gutter_width = self
+            .spans
+            .iter()
+            .chain(self.children.iter().flat_map(|child| child.spans.iter()))
+            .map(|span| span.line_end.to_string().len())
+            .max()
+            .unwrap_or_default();
+
+        let mut rendered = format!("{}\n", self.header(ansi));
+        self.render_spans(&mut rendered, gutter_width, ansi);
+
+        let mut notes = self.children.iter().filter(|child| child.spans.is_empty());
+        if let Some(first) = notes.next() {
+            let gutter = gutter(gutter_width, ansi);
+
+            if !self.spans.is_empty() {
+                rendered.push_str(&format!("{gutter}\n"));
+            }
+            for note in std::iter::once(first).chain(notes) {
+                rendered.push_str(&format!(
+                    "{}= {}: {}\n",
+                    " ".repeat(gutter_width + 1),
+                    note.level,
+                    note.message
+                ));
+            }
+        }
+
+        for child in self.children.iter().filter(|child| !child.spans.is_empty()) {
+            rendered.push_str(&format!("{}\n", child.header(ansi)));
+            child.render_spans(&mut rendered, gutter_width, ansi);
+        }
+
+        rendered.push('\n');
+
+        rendered
+    }
+
+    fn render_spans(&self, rendered: &mut String, gutter_width: usize, ansi: bool) {
+        let primary = match self.spans.iter().find(|span| span.is_primary) {
+            Some(span) => span,
+            None => return,
+        };
+        let gutter = gutter(gutter_width, ansi);
+
+        rendered.push_str(&format!(
+            "{}{}--> {}{}:{}:{}\n",
+            " ".repeat(gutter_width),
+            blue(ansi),
+            reset(ansi),
+            primary.file_name,
+            primary.line_start,
+            primary.column_start
+        ));
+        rendered.push_str(&format!("{gutter}\n"));
+
+        for span in self.spans.iter() {
+            for (i, line) in span.text.iter().enumerate() {
+                let marker = match span.is_primary {
+                    true => "^",
+                    false => "-",
+                };
+                let label = match (&span.label, &span.suggested_replacement) {
+                    (_, Some(replacement)) => format!(" {}: `{replacement}`", self.message),
+                    (Some(label), _) if i + 1 == span.text.len() => format!(" {label}"),
+                    _ => String::new(),
+                };
+                let highlight = line
+                    .highlight_end
+                    .saturating_sub(line.highlight_start)
+                    .max(1);
+
+                rendered.push_str(&format!(
+                    "{}{:>gutter_width$} |{} {}\n",
+                    blue(ansi),
+                    span.line_start + i,
+                    reset(ansi),
+                    line.text
+                ));
+                rendered.push_str(&format!(
+                    "{gutter} {}{}{}{}{}\n",
+                    " ".repeat(line.highlight_start.saturating_sub(1)),
+                    color(&self.level, ansi),
+                    marker.repeat(highlight),
+                    label,
+                    reset(ansi)
+                ));
+            }
+        }
+    }
+}
+
+fn color(level: &str, ansi: bool) -> &'static str {
+    match (ansi, level) {
+        (false, _) => "",
+        (true, "warning") => "\x1b[1m\x1b[33m",
+        (true, "note") => "\x1b[1m\x1b[32m",
+        (true, "help") => "\x1b[1m\x1b[36m",
+        (true, _) => "\x1b[1m\x1b[31m",
+    }
+}
+
+fn blue(ansi: bool) -> &'static str {
+    match ansi {
+        true => "\x1b[1m\x1b[34m",
+        false => "",
+    }
+}
+
+fn reset(ansi: bool) -> &'static str {
+    match ansi {
+        true => "\x1b[0m",
+        false => "",
+    }
+}
+
+fn gutter(width: usize, ansi: bool) -> String {
+    format!("{}{} |{}", blue(ansi), " ".repeat(width), reset(ansi))
+}
+
+/// Convert the standard error of `gccrs` into `rustc` JSON diagnostics. Output which
+/// is not part of GCC's JSON diagnostics, such as linker errors, is reported as a
+/// single diagnostic.
+///
+/// Diagnostics found in `reported` are skipped, and the other ones are added to it: The
+/// front-end runs again when generating code for a pipelined crate, and must not report
+/// the same diagnostics twice
+pub fn convert(
+    stderr: &str,
+    success: bool,
+    json: &[JsonOption],
+    reported: &mut Vec<Diagnostic>,
+) -> Vec<Diagnostic> {
+    let mut sources = SourceCache::default();
+    let mut diagnostics = Vec::new();
+    let mut other_output = Vec::new();
+
+    for line in stderr.lines() {
+        match serde_json::from_str::<Vec<GccDiagnostic>>(line) {
+            Ok(gcc_diagnostics) => {
+                for gcc in gcc_diagnostics.iter() {
+                    // Only top-level diagnostics are rendered
+                    let mut diagnostic = Diagnostic::from_gcc(&mut sources, gcc);
+                    diagnostic.rendered = Some(diagnostic.render(json));
+
+                    if !reported.contains(&diagnostic) {
+                        reported.push(diagnostic.clone());
+                        diagnostics.push(diagnostic);
+                    }
+                }
+            }
+            Err(_) if line.trim().is_empty() => {}
+            Err(_) => other_output.push(line),
+        }
+    }
+
+    if !other_output.is_empty() {
+        let level = match success {
+            true => "warning",
+            false => "error",
+        };
+
+        diagnostics.push(Diagnostic::message(level, &other_output.join("\n")));
+    }
+
+    let errors = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.level.starts_with("error"))
+        .count();
+    match errors {
+        0 => {}
+        1 => diagnostics.push(Diagnostic::message(
+            "error",
+            "aborting due to 1 previous error",
+        )),
+        n => diagnostics.push(Diagnostic::message(
+            "error",
+            &format!("aborting due to {n} previous errors"),
+        )),
+    }
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gcc_json() {
+        let dir = tempdir::TempDir::new("diagnostics").unwrap();
+        let file = dir.path().join("main.rs");
+        std::fs::write(&file, "fn main() {\n    let a = b;\n}\n").unwrap();
+        let file = file.display().to_string();
+
+        let stderr = format!(
+            r#"[{{"kind": "error", "message": "cannot find value `b` in this scope", "children": [{{"kind": "note", "message": "a note", "locations": []}}], "locations": [{{"caret": {{"file": "{file}", "line": 2, "display-column": 13, "byte-column": 13, "column": 13}}, "finish": {{"file": "{file}", "line": 2, "display-column": 13, "byte-column": 13, "column": 13}}}}], "metadata": {{"rules": [{{"id": "E0425"}}]}}}}]"#
+        );
+
+        let diagnostics = convert(&stderr, false, &[], &mut Vec::new());
+        assert_eq!(diagnostics.len(), 2);
+
+        let error = &diagnostics[0];
+        assert_eq!(error.level, "error");
+        assert_eq!(error.code.as_ref().unwrap().code, "E0425");
+        assert_eq!(error.spans[0].byte_start, 24);
+        assert_eq!(error.spans[0].byte_end, 25);
+        assert_eq!(error.spans[0].column_end, 14);
+        assert_eq!(error.spans[0].text[0].text, "    let a = b;");
+        assert_eq!(error.children[0].level, "note");
+        assert_eq!(
+            error.rendered.as_deref().unwrap(),
+            format!(
+                "error[E0425]: cannot find value `b` in this scope\n \
+                 --> {file}:2:13\n  \
+                 |\n\
+                 2 |     let a = b;\n  \
+                 |             ^\n  \
+                 |\n  \
+                 = note: a note\n\n"
+            )
+        );
+
+        assert_eq!(diagnostics[1].message, "aborting due to 1 previous error");
+    }
+
+    #[test]
+    fn reported_diagnostics() {
+        let stderr = r#"[{"kind": "warning", "message": "unused name 'a'", "locations": [], "option": "-Wunused-variable"}]"#;
+        let mut reported = Vec::new();
+
+        assert_eq!(convert(stderr, true, &[], &mut reported).len(), 1);
+        // The codegen pass of a pipelined crate runs the front-end again
+        assert!(convert(stderr, true, &[], &mut reported).is_empty());
+        assert_eq!(reported.len(), 1);
+    }
+
+    #[test]
+    fn plain_output() {
+        let diagnostics = convert(
+            "/usr/bin/ld: cannot find -lfoo\ncollect2: error: ld returned 1 exit status\n",
+            false,
+            &[],
+            &mut Vec::new(),
+        );
+
+        assert_eq!(diagnostics[0].level, "error");
+        assert!(diagnostics[0].message.contains("cannot find -lfoo"));
+        assert!(serde_json::to_string(&diagnostics[0])
+            .unwrap()
+            .starts_with(r#"{"$message_type":"diagnostic","message":"#));
+    }
+
+    #[test]
+    fn artifact_notification() {
+        let notification =
+            ArtifactNotification::new(Path::new("deps/libfoo.rmeta"), EmitKind::Metadata);
+
+        assert_eq!(
+            serde_json::to_string(&notification).unwrap(),
+            r#"{"$message_type":"artifact","artifact":"deps/libfoo.rmeta","emit":"metadata"}"#
+        );
+    }
+}
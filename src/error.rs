@@ -15,6 +15,21 @@ pub enum Error {
     /// Invalid config line dumped when executing `gccrs -frust-dump-*`
     #[error("Invalid configuration returned when executing `gccrs -frust-dump-*`")]
     InvalidCfgDump,
+    /// Invalid version string returned when executing `gccrs --version`
+    #[error("Invalid version returned when executing `gccrs --version`")]
+    InvalidVersion,
+    /// Invalid line found in the link information stored next to a Rust library
+    #[error("Invalid link information next to a `gccrs` library: `{0}`")]
+    InvalidLinkInfo(String),
+    /// A library compiled with another panic strategy cannot be linked with the crate
+    #[error("`{0}` was compiled with the panic strategy `{1}`, which is incompatible with this crate's strategy `{2}`")]
+    PanicStrategy(String, String, String),
+    /// The requested sanitizers are not supported by GCC
+    #[error("Unsupported sanitizers: {0}")]
+    Sanitizer(String),
+    /// Invalid coverage data returned when executing `gcov --json-format`
+    #[error("Invalid coverage data returned when executing `gcov`: {0}")]
+    InvalidCoverage(String),
     /// Error when compiling a program using `gccrs`
     #[error("Error when compiling project using `gccrs`")]
     Compile,
@@ -36,6 +51,21 @@ pub enum Error {
     /// The `cargo-gccrs` process did not complete successfully
     #[error("`cargo-gccrs` did not complete succesfully")]
     WrapperExit,
+    /// The workspace could not be queried using `cargo metadata`
+    #[error("Could not query the workspace using `cargo metadata`")]
+    Metadata,
+    /// The training command given to `cargo gccrs pgo` did not complete successfully
+    #[error("The profile-guided optimization training command failed")]
+    PgoTraining,
+    /// Test crates need a test harness, which `gccrs` cannot generate
+    #[error("`gccrs` cannot build test harnesses yet, which `--test` and `cargo test` need")]
+    TestHarness,
+    /// The command given to `cargo gccrs cov` did not complete successfully
+    #[error("The command measured by `cargo gccrs cov` failed")]
+    CoverageRun,
+    /// The coverage data collected by `cargo gccrs cov` could not be read
+    #[error("Error when collecting coverage data using `gcov`")]
+    Coverage,
 }
 
 // If parsing the options using `getopts` fail, then it was because an unhandled argument
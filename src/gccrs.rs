@@ -1,14 +1,33 @@
 //! This module aims at abstracting the usage of `gccrs` via Rust code. This is a simple
 //! wrapper around spawning a `gccrs` command with various arguments
 
-use super::args::{Args, ArgsCollection, CrateType};
-use super::{config::GccrsConfig, env_args::EnvArgs, rustc_args::RustcArgs, Error, Result};
+use super::args::{Args, ArgsCollection, Artifact};
+use super::rustc_args::{
+    CrateType, EmitKind, ErrorFormat, JsonOption, PrintRequest, RustcInvocation,
+};
+use super::{
+    config::GccrsConfig,
+    dep_info::DepInfo,
+    dependencies::LinkInfo,
+    diagnostics::{self, ArtifactNotification, Diagnostic},
+    env_args::EnvArgs,
+    target::Target,
+    target_features,
+    version::VersionInfo,
+    Error, Result,
+};
 
 use std::convert::TryFrom;
-use std::process::{Command, ExitStatus};
+use std::ffi::{OsStr, OsString};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
 
 pub struct Gccrs;
 
+/// File in which `gccrs` dumps the HIR of the crate being compiled
+const HIR_DUMP_FILE: &str = "gccrs.hir-pretty.dump";
+
 /// Internal type to use when executing commands. The errors should be converted into
 /// [`Error`]s using the `?` operator.
 type CmdResult<T = ()> = std::io::Result<T>;
@@ -19,19 +38,14 @@ impl Gccrs {
         Err(Error::Installation)
     }
 
-    /// Output fake information because gccrs does not implement the required feature
-    /// yet. This function is only available in debug mode, not release, in order for
-    /// users to be aware of the limitations.
-    fn fake_output(s: &str) {
-        println!("{s}");
-    }
-
-    fn dump_config() -> CmdResult<ExitStatus> {
+    fn dump_config(machine_args: &[String]) -> CmdResult<ExitStatus> {
         Command::new("gccrs")
+            .args(machine_args)
             .arg("-x")
             .arg("rust")
             .arg("-frust-dump-target_options")
             .arg("-")
+            .stdin(Stdio::null())
             .status()
     }
 
@@ -47,20 +61,130 @@ impl Gccrs {
         }
     }
 
-    fn cfg_print() -> Result {
-        // FIXME: The output needs to be adapted based on the target triple. For example,
-        // produce a .dll on windows, etc etc
-        Gccrs::fake_output(r#"___"#);
-        Gccrs::fake_output(r#"lib___.rlib"#);
-        Gccrs::fake_output(r#"lib___.so"#);
-        Gccrs::fake_output(r#"lib___.so"#);
-        Gccrs::fake_output(r#"lib___.a"#);
-        Gccrs::fake_output(r#"lib___.so"#);
+    fn cfg_print(invocation: &RustcInvocation) -> Result {
+        // `gccrs` reports the target features enabled by the requested CPU and features,
+        // including the ones they imply
+        let target = Gccrs::target(invocation)?;
+        Gccrs::dump_config(&target_features::machine_args(
+            &target,
+            &invocation.codegen,
+        )?)?;
+        let mut config = GccrsConfig::new()?;
+        config.add_cfgs(&invocation.all_cfgs());
+        config.set_target_features(&invocation.codegen.target_features);
+
+        print!("{config}");
+
+        Ok(())
+    }
+
+    /// Get the standard output of a successful command
+    fn command_output(cmd: &mut Command) -> Result<String> {
+        let output = cmd.output()?;
 
-        Gccrs::dump_config()?;
-        let config = GccrsConfig::new()?;
+        match output.status.success() {
+            true => Ok(String::from_utf8(output.stdout).map_err(|_| Error::Utf8(None))?),
+            false => Err(Error::Command(std::io::Error::other(format!(
+                "{cmd:?} did not exit successfully"
+            )))),
+        }
+    }
 
-        println!("{config}");
+    /// Get the target triple to compile for: Either the one given with `--target`, or
+    /// the host's
+    fn target(invocation: &RustcInvocation) -> Result<Target> {
+        match &invocation.target {
+            Some(target) => Target::parse(target),
+            None => Target::parse(&Gccrs::command_output(
+                Command::new("gcc").arg("-dumpmachine"),
+            )?),
+        }
+    }
+
+    /// Get the installation prefix of `gccrs`. Unless `gccrs` was configured with a
+    /// specific sysroot, this is the parent of the directory containing the binary
+    fn sysroot() -> Result<PathBuf> {
+        let sysroot = Gccrs::command_output(Command::new("gccrs").arg("-print-sysroot"))?;
+
+        match sysroot.trim() {
+            "" => which::which("gccrs")
+                .ok()
+                .and_then(|gccrs| Some(gccrs.parent()?.parent()?.to_owned()))
+                .ok_or(Error::Installation),
+            sysroot => Ok(PathBuf::from(sysroot)),
+        }
+    }
+
+    /// Get the directory containing the target's runtime libraries
+    fn target_libdir() -> Result<PathBuf> {
+        let libgcc = Gccrs::command_output(Command::new("gccrs").arg("-print-libgcc-file-name"))?;
+
+        Ok(PathBuf::from(libgcc.trim())
+            .parent()
+            .map(PathBuf::from)
+            .unwrap_or_default())
+    }
+
+    /// Answer every `--print` request, in the order in which they were given
+    fn print_requests(invocation: &RustcInvocation) -> Result {
+        let target = Gccrs::target(invocation)?;
+
+        for request in invocation.print.iter() {
+            match request {
+                PrintRequest::FileNames => {
+                    let name = format!(
+                        "{}{}",
+                        invocation.output_crate_name(),
+                        invocation
+                            .codegen
+                            .extra_filename
+                            .as_deref()
+                            .unwrap_or_default()
+                    );
+
+                    invocation
+                        .crate_types_or_default()
+                        .iter()
+                        .for_each(|crate_type| println!("{}", target.file_name(*crate_type, &name)))
+                }
+                PrintRequest::Sysroot => println!("{}", Gccrs::sysroot()?.display()),
+                PrintRequest::Cfg => Gccrs::cfg_print(invocation)?,
+                PrintRequest::CrateName => println!("{}", invocation.output_crate_name()),
+                PrintRequest::TargetLibdir => println!("{}", Gccrs::target_libdir()?.display()),
+                // Debug information is split using `objcopy` or `-gsplit-dwarf`
+                PrintRequest::SplitDebuginfo => println!("off\npacked\nunpacked"),
+                // `gccrs` can only compile for the target it was configured for
+                PrintRequest::TargetList => println!("{target}"),
+                // Static libraries are only known once compiled: This is handled in
+                // [`Gccrs::generate_static_lib`]
+                PrintRequest::NativeStaticLibs => {}
+                request => {
+                    return Err(Error::InvalidArg(format!(
+                        "`--print {request}` is not supported by gccrs"
+                    )))
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn version_info() -> Result<VersionInfo> {
+        let gccrs_version = Gccrs::command_output(Command::new("gccrs").arg("--version"))?;
+        let machine = Gccrs::command_output(Command::new("gcc").arg("-dumpmachine"))?;
+
+        VersionInfo::new(&gccrs_version, &machine)
+    }
+
+    /// Answer `rustc --version` and `rustc -vV`, which `cargo` uses to query the host
+    /// triple and to fingerprint the compiler
+    fn print_version(verbose: bool) -> Result {
+        let version = Gccrs::version_info()?;
+
+        match verbose {
+            true => println!("{version}"),
+            false => println!("{}", version.short()),
+        }
 
         Ok(())
     }
@@ -69,9 +193,61 @@ impl Gccrs {
         Command::new("gccrs").args(args).status()
     }
 
+    /// Spawn `gccrs` with GCC's JSON diagnostics, and convert the ones which were not
+    /// already reported into `rustc` diagnostics on stderr. They are given as JSON if
+    /// requested, and rendered like `rustc` does otherwise
+    fn spawn_with_json_diagnostics(
+        args: &[String],
+        invocation: &RustcInvocation,
+        reported: &mut Vec<Diagnostic>,
+    ) -> CmdResult<ExitStatus> {
+        let output = Command::new("gccrs")
+            .args(args)
+            .arg("-fdiagnostics-format=json")
+            .stdout(Stdio::inherit())
+            .output()?;
+
+        let json = match invocation.error_format {
+            Some(ErrorFormat::Json) => invocation.json.clone(),
+            Some(ErrorFormat::Short) => vec![JsonOption::DiagnosticShort],
+            _ => vec![],
+        };
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        for diagnostic in diagnostics::convert(&stderr, output.status.success(), &json, reported) {
+            match invocation.error_format {
+                Some(ErrorFormat::Json) => eprintln!("{}", serde_json::to_string(&diagnostic)?),
+                _ => eprint!("{}", diagnostic.rendered().unwrap_or_default()),
+            }
+        }
+
+        Ok(output.status)
+    }
+
+    /// Tell `cargo` that an output was written, if it asked for it
+    fn notify_artifact(invocation: &RustcInvocation, path: &Path, emit: EmitKind) -> Result {
+        let notify = invocation.error_format == Some(ErrorFormat::Json)
+            && invocation.json.contains(&JsonOption::Artifacts);
+
+        if notify {
+            eprintln!(
+                "{}",
+                serde_json::to_string(&ArtifactNotification::new(path, emit))
+                    .map_err(std::io::Error::from)?
+            );
+        }
+
+        Ok(())
+    }
+
     /// Spawn a `gccrs` command with arguments extracted from a `rustc` invokation
-    fn compile(gccrs_args: &Args) -> Result {
-        let exit_status = Gccrs::spawn_with_args(&gccrs_args.as_args()?)?;
+    fn compile(gccrs_args: &[String], invocation: &RustcInvocation) -> Result {
+        let exit_status = match invocation.error_format {
+            Some(ErrorFormat::Json) => {
+                Gccrs::spawn_with_json_diagnostics(gccrs_args, invocation, &mut Vec::new())?
+            }
+            _ => Gccrs::spawn_with_args(gccrs_args)?,
+        };
 
         match exit_status.success() {
             false => Err(Error::Compile),
@@ -79,63 +255,308 @@ impl Gccrs {
         }
     }
 
-    /// Execute a callback if necessary, based on the different options used to build
-    /// the `gccrs` arguments
-    fn maybe_callback(gccrs_args: &Args) -> Result {
-        // If we are ordered to generate a static library, call `ar` after compiling
-        // the object files
-        if gccrs_args.crate_type() == CrateType::StaticLib {
-            Gccrs::generate_static_lib(gccrs_args)?;
+    /// Spawn one of the two `gccrs` passes compiling a pipelined crate. Both run the
+    /// front-end, whose diagnostics are only reported once, while the diagnostics of the
+    /// middle-end and of the backend only come from the second pass
+    fn compile_pass(
+        gccrs_args: &[String],
+        invocation: &RustcInvocation,
+        reported: &mut Vec<Diagnostic>,
+    ) -> Result {
+        match Gccrs::spawn_with_json_diagnostics(gccrs_args, invocation, reported)?.success() {
+            false => Err(Error::Compile),
+            true => Ok(()),
+        }
+    }
+
+    /// Link the crate's object file, using the compiler driver given with `-C linker` if
+    /// any. Other drivers may not understand GCC's JSON diagnostics, so their output is
+    /// given to `cargo` as is
+    fn link(gccrs_args: &Args, link_args: &[String], invocation: &RustcInvocation) -> Result {
+        let linker = match gccrs_args.linker_program() {
+            Some(linker) => linker,
+            None => return Gccrs::compile(link_args, invocation),
+        };
+
+        match Command::new(linker).args(link_args).status()?.success() {
+            false => Err(Error::Compile),
+            true => Ok(()),
+        }
+    }
+
+    /// Produce an artifact once the crate has been compiled to an object file
+    fn produce_artifact(
+        gccrs_args: &Args,
+        artifact: &Artifact,
+        invocation: &RustcInvocation,
+        target: &Target,
+    ) -> Result {
+        match (artifact.emit, artifact.crate_type) {
+            (
+                EmitKind::Link,
+                Some(crate_type @ (CrateType::Bin | CrateType::DyLib | CrateType::CDyLib)),
+            ) => Gccrs::link(
+                gccrs_args,
+                &gccrs_args.link_args_for(artifact, crate_type)?,
+                invocation,
+            )?,
+            // If we are ordered to generate a static library or a Rust library, call
+            // `ar` on the object file
+            (EmitKind::Link, Some(CrateType::StaticLib)) => {
+                Gccrs::generate_static_lib(gccrs_args, artifact, CrateType::StaticLib)?;
+
+                if invocation.print.contains(&PrintRequest::NativeStaticLibs) {
+                    Gccrs::print_native_static_libs(gccrs_args, target)?;
+                }
+            }
+            (EmitKind::Link, Some(crate_type @ (CrateType::Lib | CrateType::Rlib))) => {
+                Gccrs::generate_static_lib(gccrs_args, artifact, crate_type)?;
+
+                // Remember the library's dependencies for the final link
+                LinkInfo::new(
+                    gccrs_args.dependencies(),
+                    &gccrs_args.native_args(crate_type)?,
+                    Some(gccrs_args.panic_strategy()),
+                )
+                .write(&artifact.path)?;
+            }
+            // The metadata artifact expected by `cargo` is a copy of the metadata
+            // exported by `gccrs`. Crates exporting nothing, such as binaries, still get
+            // an empty one so that `cargo check` considers them up to date
+            (EmitKind::Metadata, _) => match gccrs_args.metadata_export() {
+                Some(metadata_export) if metadata_export.exists() => {
+                    std::fs::copy(metadata_export, &artifact.path)?;
+                }
+                _ => {
+                    std::fs::File::create(&artifact.path)?;
+                }
+            },
+            (EmitKind::Obj, _) => {
+                std::fs::copy(gccrs_args.object_file(), &artifact.path)?;
+            }
+            (EmitKind::Asm, _) => Gccrs::compile(&gccrs_args.asm_args(artifact)?, invocation)?,
+            (EmitKind::Mir, _) => std::fs::rename(HIR_DUMP_FILE, &artifact.path)?,
+            // Other dumps are written while compiling the crate
+            _ => {}
         }
 
         Ok(())
     }
 
-    fn translate_and_compile(args: &[String]) -> Result {
-        let rustc_args = RustcArgs::try_from(args)?;
-        let gccrs_args = ArgsCollection::try_from(&rustc_args)?;
+    /// Write the dependency information of the crate, with a rule for each of the
+    /// produced outputs
+    fn write_dep_info(invocation: &RustcInvocation, gccrs_args: &ArgsCollection) -> Result {
+        let input = match invocation.input.as_deref() {
+            Some("-") | None => return Ok(()),
+            Some(input) => Path::new(input),
+        };
+
+        let dep_info_path = gccrs_args.outputs().path(EmitKind::DepInfo);
+        let mut targets: Vec<PathBuf> = gccrs_args
+            .artifacts()
+            .iter()
+            .map(|artifact| artifact.path.clone())
+            .collect();
+        targets.push(dep_info_path.clone());
+        targets.dedup();
+
+        // Older `gccrs` versions do not write make rules, and the crate is scanned
+        // instead
+        let dep_info = match gccrs_args.args().dep_file() {
+            Some(dep_file) if dep_file.exists() => {
+                let dep_info = DepInfo::from_makefile(&std::fs::read_to_string(dep_file)?)?;
+                std::fs::remove_file(dep_file)?;
+
+                dep_info
+            }
+            _ => DepInfo::scan(input)?,
+        };
+
+        dep_info.write(&dep_info_path, &targets)
+    }
+
+    fn translate_and_compile(invocation: &RustcInvocation) -> Result {
+        let target = Gccrs::target(invocation)?;
+        let gccrs_args = ArgsCollection::new(invocation, &target)?;
+        let args = gccrs_args.args();
+
+        // Produce and announce the crate metadata first so that `cargo` can start
+        // compiling dependent crates while we generate code, if we need to at all
+        let (metadata, artifacts): (Vec<&Artifact>, Vec<&Artifact>) = gccrs_args
+            .artifacts()
+            .iter()
+            .partition(|artifact| artifact.emit == EmitKind::Metadata);
 
-        for arg_set in gccrs_args.data().iter() {
-            Gccrs::compile(arg_set)?;
-            Gccrs::maybe_callback(arg_set)?;
+        let mut reported = Vec::new();
+        if args.pipelined() {
+            Gccrs::compile_pass(&args.metadata_args()?, invocation, &mut reported)?;
+        }
+
+        for artifact in metadata.into_iter() {
+            Gccrs::produce_artifact(args, artifact, invocation, &target)?;
+            Gccrs::notify_artifact(invocation, &artifact.path, artifact.emit)?;
+        }
+
+        // Compile the crate once, and use the resulting object file for every artifact
+        if args.codegen() {
+            match args.pipelined() {
+                true => Gccrs::compile_pass(&args.compile_args()?, invocation, &mut reported)?,
+                false => Gccrs::compile(&args.compile_args()?, invocation)?,
+            }
+
+            for artifact in artifacts.into_iter() {
+                Gccrs::produce_artifact(args, artifact, invocation, &target)?;
+                // Like `rustc`, only the artifact itself is announced, and not the
+                // companion file holding its debug information
+                Gccrs::split_and_strip(args, artifact)?;
+                Gccrs::notify_artifact(invocation, &artifact.path, artifact.emit)?;
+            }
+
+            std::fs::remove_file(args.object_file())?;
+        }
+
+        let outputs = gccrs_args.outputs();
+        if outputs.contains(EmitKind::DepInfo) {
+            Gccrs::write_dep_info(invocation, &gccrs_args)?;
+            Gccrs::notify_artifact(
+                invocation,
+                &outputs.path(EmitKind::DepInfo),
+                EmitKind::DepInfo,
+            )?;
         }
 
         Ok(())
     }
 
-    fn generate_static_lib(args: &Args) -> Result {
-        let output_file = args
-            .output_file()
-            .to_str()
-            .expect("Cannot handle non-unicode filenames yet");
+    /// Display the native libraries needed when linking against a static library, in
+    /// the same format as `rustc`
+    fn print_native_static_libs(args: &Args, target: &Target) -> Result {
+        let mut libs = args.native_args(CrateType::StaticLib)?;
+        libs.extend(
+            target
+                .native_static_libs()
+                .iter()
+                .map(|lib| format!("-l{lib}")),
+        );
+
+        eprintln!("note: Link against the following native artifacts when linking against this static library. The order and any duplication can be significant on some platforms.");
+        eprintln!();
+        eprintln!("note: native-static-libs: {}", libs.join(" "));
+        eprintln!();
+
+        Ok(())
+    }
+
+    fn generate_static_lib(args: &Args, artifact: &Artifact, crate_type: CrateType) -> Result {
+        let output_file = artifact.path.clone().into_os_string().into_string()?;
+
+        // `ar` adds files to existing archives: Start from scratch instead
+        if artifact.path.exists() {
+            std::fs::remove_file(&artifact.path)?;
+        }
 
         let mut ar_args = vec![
             String::from("rcs"), // Create the archive and add the files to it
-            output_file.to_owned(),
-            args.object_file_name().into_os_string().into_string()?,
+            output_file,
+            args.object_file()
+                .to_owned()
+                .into_os_string()
+                .into_string()?,
         ];
 
         if let Some(mut extra_ar_args) = EnvArgs::Ar.as_args() {
             ar_args.append(&mut extra_ar_args);
         }
 
-        Command::new("ar").args(ar_args).status()?;
+        if !Command::new(args.archiver())
+            .args(ar_args)
+            .status()?
+            .success()
+        {
+            return Err(Error::Compile);
+        }
+
+        let bundled_libs = args.bundled_libs(crate_type)?;
+        if !bundled_libs.is_empty() {
+            Gccrs::bundle_native_libs(args.archiver(), &artifact.path, &bundled_libs)?;
+        }
+
+        Ok(())
+    }
+
+    /// Merge native static libraries inside of an existing archive. This uses an `ar`
+    /// MRI script, since `ar` cannot add archives to another one otherwise
+    fn bundle_native_libs(archiver: &str, archive: &Path, libs: &[PathBuf]) -> Result {
+        let mut script = format!("OPEN {}\n", archive.display());
+        libs.iter()
+            .for_each(|lib| script.push_str(&format!("ADDLIB {}\n", lib.display())));
+        script.push_str("SAVE\nEND\n");
+
+        let mut ar = Command::new(archiver)
+            .arg("-M")
+            .stdin(Stdio::piped())
+            .spawn()?;
+        ar.stdin
+            .take()
+            .expect("`ar` was spawned with a piped stdin")
+            .write_all(script.as_bytes())?;
+
+        match ar.wait()?.success() {
+            true => Ok(()),
+            false => Err(Error::Compile),
+        }
+    }
+
+    fn objcopy(args: &[&OsStr]) -> Result {
+        match Command::new("objcopy").args(args).status()?.success() {
+            true => Ok(()),
+            false => Err(Error::Compile),
+        }
+    }
+
+    /// Move the debug information of a linked executable or shared object to its
+    /// companion file, and strip it, as requested
+    fn split_and_strip(args: &Args, artifact: &Artifact) -> Result {
+        let linked = matches!(
+            artifact.crate_type,
+            Some(CrateType::Bin | CrateType::DyLib | CrateType::CDyLib)
+        );
+        if artifact.emit != EmitKind::Link || !linked {
+            return Ok(());
+        }
+
+        let path = artifact.path.as_os_str();
+        if let Some(debuginfo) = args.debuginfo_file(artifact) {
+            let mut debuglink = OsString::from("--add-gnu-debuglink=");
+            debuglink.push(&debuginfo);
+
+            Gccrs::objcopy(&[OsStr::new("--only-keep-debug"), path, debuginfo.as_os_str()])?;
+            Gccrs::objcopy(&[OsStr::new("--strip-debug"), &debuglink, path])?;
+        }
+
+        if let Some(strip) = args.strip_option() {
+            Gccrs::objcopy(&[OsStr::new(strip), path])?;
+        }
 
         Ok(())
     }
 
     /// Convert arguments given to `rustc` into valid arguments for `gccrs`
     pub fn compile_with_rust_args(args: &[String]) -> Result {
-        let first_rustc_arg = args.get(2);
-
-        match first_rustc_arg.map(|s| s.as_str()) {
-            // FIXME: Is that true? Should we use getopts and just parse it and convert
-            // it as well?
-            // If rustc is invoked with stdin as input, then it's simply to print the
-            // configuration option in our case, since we are compiling a rust project
-            // with files and crates
-            Some("-") => Gccrs::cfg_print(),
-            _ => Gccrs::translate_and_compile(args),
+        // Skip `gccrs-driver` and `rustc` in the invocation
+        let invocation = RustcInvocation::try_from(&args[2..])?;
+
+        // Like `rustc`, stop after printing the requested information. Native static
+        // libraries are the exception, as they are printed while compiling
+        let print_only = invocation
+            .print
+            .iter()
+            .any(|request| *request != PrintRequest::NativeStaticLibs);
+
+        match invocation.version {
+            true => Gccrs::print_version(invocation.verbose),
+            false if print_only => Gccrs::print_requests(&invocation),
+            false => Gccrs::translate_and_compile(&invocation),
         }
     }
 }
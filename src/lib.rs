@@ -1,9 +1,20 @@
 mod args;
+pub mod codegen_options;
 mod config;
+pub mod coverage;
+mod dep_info;
+mod dependencies;
+mod diagnostics;
 mod env_args;
 mod error;
 mod gccrs;
-mod rustc_args;
+mod lints;
+mod outputs;
+pub mod rustc_args;
+mod sanitizers;
+mod target;
+mod target_features;
+mod version;
 
 pub use error::Error;
 pub use gccrs::Gccrs;
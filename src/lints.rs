@@ -0,0 +1,146 @@
+//! Translate the lint levels given with `-A`, `-W`, `--force-warn`, `-D`, `-F` and
+//! `--cap-lints` into GCC's warning options. `gccrs` reports most of its lints as
+//! warnings controlled by a `-W` option, which can be disabled with `-Wno-` or turned
+//! into an error with `-Werror=`. Lints `gccrs` does not implement are never reported,
+//! and are ignored.
+
+use super::rustc_args::{LintLevel, LintOption};
+
+/// `rustc` lints and lint groups, followed by the `gccrs` warnings reporting them
+const LINTS: &[(&str, &[&str])] = &[
+    ("dead_code", &["unused-function"]),
+    ("overflowing_literals", &["overflow"]),
+    ("unreachable_code", &["unreachable-code"]),
+    ("unused_assignments", &["unused-but-set-variable"]),
+    ("unused_macros", &["unused-macros"]),
+    ("unused_must_use", &["unused-result"]),
+    ("unused_variables", &["unused-variable"]),
+    (
+        "unused",
+        &[
+            "unused-function",
+            "unused-but-set-variable",
+            "unused-macros",
+            "unused-result",
+            "unused-variable",
+        ],
+    ),
+];
+
+/// Get the `gccrs` warnings reporting a lint. `rustc` accepts dashes in lint names
+fn gcc_warnings(lint: &str) -> &'static [&'static str] {
+    let lint = lint.replace('-', "_");
+
+    LINTS
+        .iter()
+        .find(|(name, _)| *name == lint)
+        .map(|(_, warnings)| *warnings)
+        .unwrap_or_default()
+}
+
+/// Get the name of the `rustc` lint reported through a GCC warning option, such as
+/// `-Wunused-variable` or `-Werror=unused-variable`
+pub fn rustc_lint(option: &str) -> Option<&'static str> {
+    let warning = option
+        .strip_prefix("-Werror=")
+        .or_else(|| option.strip_prefix("-W"))?;
+
+    // Groups come last in the table, so individual lints are found first
+    LINTS
+        .iter()
+        .find(|(_, warnings)| warnings.contains(&warning))
+        .map(|(name, _)| *name)
+}
+
+/// Translate a lint level into GCC options. The `warnings` group stands for every
+/// warning, including the ones which are not lints
+fn level_args(name: &str, level: LintLevel) -> Vec<String> {
+    if name == "warnings" {
+        let arg = match level {
+            LintLevel::Allow => "-w",
+            LintLevel::Warn | LintLevel::ForceWarn => "-Wno-error",
+            LintLevel::Deny | LintLevel::Forbid => "-Werror",
+        };
+
+        return vec![String::from(arg)];
+    }
+
+    gcc_warnings(name)
+        .iter()
+        .flat_map(|warning| match level {
+            LintLevel::Allow => vec![format!("-Wno-{warning}")],
+            // A lint denied earlier on the command line must become a warning again
+            LintLevel::Warn | LintLevel::ForceWarn => {
+                vec![format!("-W{warning}"), format!("-Wno-error={warning}")]
+            }
+            LintLevel::Deny | LintLevel::Forbid => vec![format!("-Werror={warning}")],
+        })
+        .collect()
+}
+
+/// Get the GCC options setting the requested lint levels, in order since later options
+/// override earlier ones. `--cap-lints` lowers every level to the given one, which is
+/// how `cargo` silences the diagnostics of dependencies
+pub fn lint_args(lints: &[LintOption], cap: Option<LintLevel>) -> Vec<String> {
+    // GCC cannot let some warnings through once every warning is disabled, so lints
+    // forced to warn are silenced as well
+    if cap == Some(LintLevel::Allow) {
+        return vec![String::from("-w")];
+    }
+
+    lints
+        .iter()
+        .flat_map(|lint| {
+            let level = match (lint.level, cap) {
+                (LintLevel::ForceWarn, _) | (_, None) => lint.level,
+                (level, Some(cap)) => level.min(cap),
+            };
+
+            level_args(&lint.name, level)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint(name: &str, level: LintLevel) -> LintOption {
+        LintOption {
+            name: name.to_owned(),
+            level,
+        }
+    }
+
+    #[test]
+    fn lint_levels() {
+        let lints = [
+            lint("warnings", LintLevel::Deny),
+            lint("unused-variables", LintLevel::Allow),
+            lint("dead_code", LintLevel::Forbid),
+            lint("clippy::all", LintLevel::Deny),
+        ];
+
+        assert_eq!(
+            lint_args(&lints, None),
+            vec!["-Werror", "-Wno-unused-variable", "-Werror=unused-function"]
+        );
+        assert_eq!(
+            lint_args(&lints, Some(LintLevel::Warn)),
+            vec![
+                "-Wno-error",
+                "-Wno-unused-variable",
+                "-Wunused-function",
+                "-Wno-error=unused-function"
+            ]
+        );
+        assert_eq!(lint_args(&lints, Some(LintLevel::Allow)), vec!["-w"]);
+    }
+
+    #[test]
+    fn lint_names() {
+        assert_eq!(rustc_lint("-Wunused-variable"), Some("unused_variables"));
+        assert_eq!(rustc_lint("-Werror=unused-result"), Some("unused_must_use"));
+        assert_eq!(rustc_lint("-Wpedantic"), None);
+    }
+}
@@ -0,0 +1,193 @@
+//! Compute the paths of the files requested using `--emit`, following `rustc`'s rules.
+//! Each output goes to the explicit path given with `--emit KIND=PATH` if any. Otherwise,
+//! it is named after the crate and placed in the output directory, or derived from the
+//! path given with `-o`.
+
+use std::path::{Path, PathBuf};
+
+use super::{
+    rustc_args::{CrateType, EmitKind, RustcInvocation},
+    target::Target,
+};
+
+/// Paths of every output requested by a `rustc` invocation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputFilenames {
+    out_dir: PathBuf,
+    /// Crate name followed by its extra filename, or the stem of the `-o` path
+    file_stem: String,
+    /// Path given with `-o`, which is used as is when a single output is requested
+    single_output: Option<PathBuf>,
+    /// Requested outputs, with their explicit path if any
+    emit: Vec<(EmitKind, Option<PathBuf>)>,
+}
+
+impl OutputFilenames {
+    pub fn new(invocation: &RustcInvocation) -> OutputFilenames {
+        let emit: Vec<(EmitKind, Option<PathBuf>)> = match invocation.emit.is_empty() {
+            // Like `rustc`, only link the crate by default
+            true => vec![(EmitKind::Link, None)],
+            false => invocation
+                .emit
+                .iter()
+                .map(|emit| (emit.kind, emit.path.clone()))
+                .collect(),
+        };
+
+        let (out_dir, file_stem) = match &invocation.output {
+            Some(output) => (
+                output.parent().map(Path::to_path_buf).unwrap_or_default(),
+                output
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .map(str::to_owned)
+                    .unwrap_or_else(|| invocation.output_crate_name()),
+            ),
+            None => (
+                invocation.out_dir.clone().unwrap_or_default(),
+                format!(
+                    "{}{}",
+                    invocation.output_crate_name(),
+                    invocation
+                        .codegen
+                        .extra_filename
+                        .as_deref()
+                        .unwrap_or_default()
+                ),
+            ),
+        };
+
+        // `-o` can only name a single output: Every other one is derived from it
+        let implicit_outputs = emit.iter().filter(|(_, path)| path.is_none()).count();
+        let single_output =
+            match implicit_outputs == 1 && invocation.crate_types_or_default().len() == 1 {
+                true => invocation.output.clone(),
+                false => None,
+            };
+
+        OutputFilenames {
+            out_dir,
+            file_stem,
+            single_output,
+            emit,
+        }
+    }
+
+    /// Check if an output kind was requested
+    pub fn contains(&self, kind: EmitKind) -> bool {
+        self.emit.iter().any(|(emit, _)| *emit == kind)
+    }
+
+    /// Get the requested output kinds, in order
+    pub fn kinds(&self) -> impl Iterator<Item = EmitKind> + '_ {
+        self.emit.iter().map(|(kind, _)| *kind)
+    }
+
+    fn explicit_path(&self, kind: EmitKind) -> Option<PathBuf> {
+        self.emit
+            .iter()
+            .find(|(emit, _)| *emit == kind)
+            .and_then(|(_, path)| path.clone())
+            .or_else(|| self.single_output.clone())
+    }
+
+    /// Get the path of an output which does not depend on the crate type
+    pub fn path(&self, kind: EmitKind) -> PathBuf {
+        if let Some(path) = self.explicit_path(kind) {
+            return path;
+        }
+
+        let file_name = match kind {
+            EmitKind::Asm => format!("{}.s", self.file_stem),
+            EmitKind::LlvmBc => format!("{}.bc", self.file_stem),
+            EmitKind::LlvmIr => format!("{}.ll", self.file_stem),
+            EmitKind::Obj => format!("{}.o", self.file_stem),
+            EmitKind::Metadata => format!("lib{}.rmeta", self.file_stem),
+            EmitKind::DepInfo => format!("{}.d", self.file_stem),
+            EmitKind::Mir => format!("{}.mir", self.file_stem),
+            EmitKind::Link => self.file_stem.clone(),
+        };
+
+        self.out_dir.join(file_name)
+    }
+
+    /// Get the path shared by the outputs, without any extension
+    pub fn base_path(&self) -> PathBuf {
+        self.out_dir.join(&self.file_stem)
+    }
+
+    /// Get the path of a temporary file used while producing the outputs
+    pub fn temp_path(&self, extension: &str) -> PathBuf {
+        self.out_dir.join(format!("{}.{extension}", self.file_stem))
+    }
+
+    /// Get the path of the linked artifact for a given crate type
+    pub fn link_path(&self, target: &Target, crate_type: CrateType) -> PathBuf {
+        match self.explicit_path(EmitKind::Link) {
+            Some(path) => path,
+            None => self
+                .out_dir
+                .join(target.file_name(crate_type, &self.file_stem)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    fn outputs(args: &[&str]) -> OutputFilenames {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+
+        OutputFilenames::new(&RustcInvocation::try_from(args.as_slice()).unwrap())
+    }
+
+    #[test]
+    fn cargo_outputs() {
+        let linux = Target::parse("x86_64-linux-gnu").unwrap();
+        let outputs = outputs(&[
+            "src/lib.rs",
+            "--crate-name",
+            "foo",
+            "--crate-type",
+            "lib",
+            "--emit=dep-info,metadata,link",
+            "-C",
+            "extra-filename=-1234",
+            "--out-dir",
+            "target/debug/deps",
+        ]);
+
+        assert!(outputs.contains(EmitKind::Metadata));
+        assert!(!outputs.contains(EmitKind::Obj));
+        assert_eq!(
+            outputs.path(EmitKind::DepInfo),
+            PathBuf::from("target/debug/deps/foo-1234.d")
+        );
+        assert_eq!(
+            outputs.path(EmitKind::Metadata),
+            PathBuf::from("target/debug/deps/libfoo-1234.rmeta")
+        );
+        assert_eq!(
+            outputs.link_path(&linux, CrateType::Lib),
+            PathBuf::from("target/debug/deps/libfoo-1234.rlib")
+        );
+    }
+
+    #[test]
+    fn explicit_outputs() {
+        let outputs = outputs(&["main.rs", "--emit=asm=out.s,obj", "-o", "build/main.o"]);
+
+        assert_eq!(outputs.path(EmitKind::Asm), PathBuf::from("out.s"));
+        assert_eq!(outputs.path(EmitKind::Obj), PathBuf::from("build/main.o"));
+    }
+
+    #[test]
+    fn derived_outputs() {
+        let outputs = outputs(&["main.rs", "--emit=asm,obj", "-o", "build/out.o"]);
+
+        assert_eq!(outputs.path(EmitKind::Asm), PathBuf::from("build/out.s"));
+        assert_eq!(outputs.path(EmitKind::Obj), PathBuf::from("build/out.o"));
+    }
+}
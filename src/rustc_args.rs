@@ -1,18 +1,960 @@
 //! This module implements `rustc`'s options parser. Ultimately, this should be directly
-//! taken from `rustc`'s implementation
+//! taken from `rustc`'s implementation. Arguments are parsed using `getopts`, and then
+//! converted into a typed [`RustcInvocation`] which can also be serialized back into
+//! a `rustc` command line.
 
-use super::{Error, Result};
-use getopts::{Matches, Options};
 use std::convert::TryFrom;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::path::{Path, PathBuf};
+
+use getopts::{Matches, Options};
+
+use super::{codegen_options::CodegenOptions, Error, Result};
+
+/// Release of `rustc` whose command line [`RustcInvocation`] understands, reported by the
+/// driver as its own version. `cargo` picks the flags it passes based on it, so it must be
+/// updated along with the parser. The newest flag supported is `--edition 2024`, stable
+/// since Rust 1.85.0
+pub const RUSTC_RELEASE: &str = "1.85.0";
 
-pub struct RustcArgs {
-    matches: Matches,
+/// Crate types that can be given to `rustc` using `--crate-type`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrateType {
+    /// Binary application
+    Bin,
+    /// Library in the compiler's preferred format
+    Lib,
+    /// Rust library
+    Rlib,
+    /// Dynamic library/Shared object
+    DyLib,
+    /// Dynamic library usable from other languages
+    CDyLib,
+    /// Statically linked library
+    StaticLib,
+    /// Procedural macro library
+    ProcMacro,
 }
 
-impl TryFrom<&[String]> for RustcArgs {
+impl TryFrom<&str> for CrateType {
     type Error = Error;
 
-    fn try_from(args: &[String]) -> Result<Self> {
+    fn try_from(s: &str) -> Result<CrateType> {
+        match s {
+            "bin" => Ok(CrateType::Bin),
+            "lib" => Ok(CrateType::Lib),
+            "rlib" => Ok(CrateType::Rlib),
+            "dylib" => Ok(CrateType::DyLib),
+            "cdylib" => Ok(CrateType::CDyLib),
+            "staticlib" => Ok(CrateType::StaticLib),
+            "proc-macro" => Ok(CrateType::ProcMacro),
+            _ => Err(Error::InvalidArg(format!("unknown crate type: `{s}`"))),
+        }
+    }
+}
+
+impl Display for CrateType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            CrateType::Bin => "bin",
+            CrateType::Lib => "lib",
+            CrateType::Rlib => "rlib",
+            CrateType::DyLib => "dylib",
+            CrateType::CDyLib => "cdylib",
+            CrateType::StaticLib => "staticlib",
+            CrateType::ProcMacro => "proc-macro",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// Kinds of output that can be requested using `--emit`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmitKind {
+    Asm,
+    LlvmBc,
+    LlvmIr,
+    Obj,
+    Metadata,
+    Link,
+    DepInfo,
+    Mir,
+}
+
+impl TryFrom<&str> for EmitKind {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<EmitKind> {
+        match s {
+            "asm" => Ok(EmitKind::Asm),
+            "llvm-bc" => Ok(EmitKind::LlvmBc),
+            "llvm-ir" => Ok(EmitKind::LlvmIr),
+            "obj" => Ok(EmitKind::Obj),
+            "metadata" => Ok(EmitKind::Metadata),
+            "link" => Ok(EmitKind::Link),
+            "dep-info" => Ok(EmitKind::DepInfo),
+            "mir" => Ok(EmitKind::Mir),
+            _ => Err(Error::InvalidArg(format!("unknown emit type: `{s}`"))),
+        }
+    }
+}
+
+impl Display for EmitKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            EmitKind::Asm => "asm",
+            EmitKind::LlvmBc => "llvm-bc",
+            EmitKind::LlvmIr => "llvm-ir",
+            EmitKind::Obj => "obj",
+            EmitKind::Metadata => "metadata",
+            EmitKind::Link => "link",
+            EmitKind::DepInfo => "dep-info",
+            EmitKind::Mir => "mir",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// A single `--emit` request, of the form `KIND[=PATH]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Emit {
+    pub kind: EmitKind,
+    pub path: Option<PathBuf>,
+}
+
+impl TryFrom<&str> for Emit {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Emit> {
+        let mut split = s.splitn(2, '=');
+
+        Ok(Emit {
+            kind: EmitKind::try_from(split.next().unwrap_or_default())?,
+            path: split.next().map(PathBuf::from),
+        })
+    }
+}
+
+impl Display for Emit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.path {
+            Some(path) => write!(f, "{}={}", self.kind, path.display()),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+/// Information that can be requested using `--print`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrintRequest {
+    AllTargetSpecsJson,
+    CallingConventions,
+    Cfg,
+    CodeModels,
+    CrateName,
+    DeploymentTarget,
+    FileNames,
+    LinkArgs,
+    NativeStaticLibs,
+    RelocationModels,
+    SplitDebuginfo,
+    StackProtectorStrategies,
+    Sysroot,
+    TargetCpus,
+    TargetFeatures,
+    TargetLibdir,
+    TargetList,
+    TargetSpecJson,
+    TlsModels,
+}
+
+impl TryFrom<&str> for PrintRequest {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<PrintRequest> {
+        match s {
+            "all-target-specs-json" => Ok(PrintRequest::AllTargetSpecsJson),
+            "calling-conventions" => Ok(PrintRequest::CallingConventions),
+            "cfg" => Ok(PrintRequest::Cfg),
+            "code-models" => Ok(PrintRequest::CodeModels),
+            "crate-name" => Ok(PrintRequest::CrateName),
+            "deployment-target" => Ok(PrintRequest::DeploymentTarget),
+            "file-names" => Ok(PrintRequest::FileNames),
+            "link-args" => Ok(PrintRequest::LinkArgs),
+            "native-static-libs" => Ok(PrintRequest::NativeStaticLibs),
+            "relocation-models" => Ok(PrintRequest::RelocationModels),
+            "split-debuginfo" => Ok(PrintRequest::SplitDebuginfo),
+            "stack-protector-strategies" => Ok(PrintRequest::StackProtectorStrategies),
+            "sysroot" => Ok(PrintRequest::Sysroot),
+            "target-cpus" => Ok(PrintRequest::TargetCpus),
+            "target-features" => Ok(PrintRequest::TargetFeatures),
+            "target-libdir" => Ok(PrintRequest::TargetLibdir),
+            "target-list" => Ok(PrintRequest::TargetList),
+            "target-spec-json" => Ok(PrintRequest::TargetSpecJson),
+            "tls-models" => Ok(PrintRequest::TlsModels),
+            _ => Err(Error::InvalidArg(format!("unknown print request: `{s}`"))),
+        }
+    }
+}
+
+impl Display for PrintRequest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            PrintRequest::AllTargetSpecsJson => "all-target-specs-json",
+            PrintRequest::CallingConventions => "calling-conventions",
+            PrintRequest::Cfg => "cfg",
+            PrintRequest::CodeModels => "code-models",
+            PrintRequest::CrateName => "crate-name",
+            PrintRequest::DeploymentTarget => "deployment-target",
+            PrintRequest::FileNames => "file-names",
+            PrintRequest::LinkArgs => "link-args",
+            PrintRequest::NativeStaticLibs => "native-static-libs",
+            PrintRequest::RelocationModels => "relocation-models",
+            PrintRequest::SplitDebuginfo => "split-debuginfo",
+            PrintRequest::StackProtectorStrategies => "stack-protector-strategies",
+            PrintRequest::Sysroot => "sysroot",
+            PrintRequest::TargetCpus => "target-cpus",
+            PrintRequest::TargetFeatures => "target-features",
+            PrintRequest::TargetLibdir => "target-libdir",
+            PrintRequest::TargetList => "target-list",
+            PrintRequest::TargetSpecJson => "target-spec-json",
+            PrintRequest::TlsModels => "tls-models",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// Kinds of search paths given to `-L`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchPathKind {
+    Native,
+    Crate,
+    Dependency,
+    Framework,
+    All,
+}
+
+impl Display for SearchPathKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            SearchPathKind::Native => "native",
+            SearchPathKind::Crate => "crate",
+            SearchPathKind::Dependency => "dependency",
+            SearchPathKind::Framework => "framework",
+            SearchPathKind::All => "all",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// A single `-L` search path, of the form `[KIND=]PATH`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchPath {
+    pub kind: SearchPathKind,
+    pub path: PathBuf,
+}
+
+impl From<&str> for SearchPath {
+    fn from(s: &str) -> SearchPath {
+        let (kind, path) = match s.split_once('=') {
+            Some(("native", path)) => (SearchPathKind::Native, path),
+            Some(("crate", path)) => (SearchPathKind::Crate, path),
+            Some(("dependency", path)) => (SearchPathKind::Dependency, path),
+            Some(("framework", path)) => (SearchPathKind::Framework, path),
+            Some(("all", path)) => (SearchPathKind::All, path),
+            // Paths are allowed to contain an equal sign
+            _ => (SearchPathKind::All, s),
+        };
+
+        SearchPath {
+            kind,
+            path: PathBuf::from(path),
+        }
+    }
+}
+
+impl Display for SearchPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}={}", self.kind, self.path.display())
+    }
+}
+
+/// An external crate given with `--extern [OPTIONS:]NAME[=PATH]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternCrate {
+    pub name: String,
+    pub path: Option<PathBuf>,
+    /// Comma-separated options such as `priv` or `noprelude`
+    pub options: Vec<String>,
+}
+
+impl TryFrom<&str> for ExternCrate {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<ExternCrate> {
+        let (spec, path) = match s.split_once('=') {
+            Some((spec, path)) => (spec, Some(PathBuf::from(path))),
+            None => (s, None),
+        };
+        let (options, name) = match spec.split_once(':') {
+            Some((options, name)) => (options.split(',').map(str::to_owned).collect(), name),
+            None => (Vec::new(), spec),
+        };
+
+        if name.is_empty() {
+            return Err(Error::InvalidArg(format!(
+                "invalid `--extern` value: `{s}`"
+            )));
+        }
+
+        Ok(ExternCrate {
+            name: name.to_owned(),
+            path,
+            options,
+        })
+    }
+}
+
+impl Display for ExternCrate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        if !self.options.is_empty() {
+            write!(f, "{}:", self.options.join(","))?;
+        }
+
+        write!(f, "{}", self.name)?;
+
+        match &self.path {
+            Some(path) => write!(f, "={}", path.display()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Kinds of native libraries given to `-l`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NativeLibKind {
+    Static,
+    Dylib,
+    Framework,
+    /// Argument given verbatim to the linker
+    LinkArg,
+    RawDylib,
+    /// No kind was given: The library is linked dynamically
+    Unspecified,
+}
+
+impl TryFrom<&str> for NativeLibKind {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<NativeLibKind> {
+        match s {
+            "static" => Ok(NativeLibKind::Static),
+            "dylib" => Ok(NativeLibKind::Dylib),
+            "framework" => Ok(NativeLibKind::Framework),
+            "link-arg" => Ok(NativeLibKind::LinkArg),
+            "raw-dylib" => Ok(NativeLibKind::RawDylib),
+            _ => Err(Error::InvalidArg(format!(
+                "unknown library kind `{s}`, expected one of: static, dylib, framework, link-arg, raw-dylib"
+            ))),
+        }
+    }
+}
+
+impl Display for NativeLibKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            NativeLibKind::Static => "static",
+            NativeLibKind::Dylib => "dylib",
+            NativeLibKind::Framework => "framework",
+            NativeLibKind::LinkArg => "link-arg",
+            NativeLibKind::RawDylib => "raw-dylib",
+            NativeLibKind::Unspecified => "",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// Modifiers which can be applied to a native library, such as `+whole-archive`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkModifier {
+    Bundle,
+    WholeArchive,
+    Verbatim,
+    AsNeeded,
+}
+
+impl TryFrom<&str> for LinkModifier {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<LinkModifier> {
+        match s {
+            "bundle" => Ok(LinkModifier::Bundle),
+            "whole-archive" => Ok(LinkModifier::WholeArchive),
+            "verbatim" => Ok(LinkModifier::Verbatim),
+            "as-needed" => Ok(LinkModifier::AsNeeded),
+            _ => Err(Error::InvalidArg(format!(
+                "unknown linking modifier `{s}`, expected one of: bundle, verbatim, whole-archive, as-needed"
+            ))),
+        }
+    }
+}
+
+impl Display for LinkModifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            LinkModifier::Bundle => "bundle",
+            LinkModifier::WholeArchive => "whole-archive",
+            LinkModifier::Verbatim => "verbatim",
+            LinkModifier::AsNeeded => "as-needed",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// A native library given with `-l [KIND[:MODIFIERS]=]NAME[:RENAME]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NativeLib {
+    pub kind: NativeLibKind,
+    /// Modifiers, with whether they are enabled (`+`) or disabled (`-`)
+    pub modifiers: Vec<(LinkModifier, bool)>,
+    pub name: String,
+    pub rename: Option<String>,
+}
+
+impl NativeLib {
+    /// Get the value of a modifier, if it was given on the command line
+    pub fn modifier(&self, modifier: LinkModifier) -> Option<bool> {
+        self.modifiers
+            .iter()
+            .rev()
+            .find(|(m, _)| *m == modifier)
+            .map(|(_, enabled)| *enabled)
+    }
+}
+
+impl TryFrom<&str> for NativeLib {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<NativeLib> {
+        let invalid = || Error::InvalidArg(format!("invalid `-l` value: `{s}`"));
+
+        let (kind, modifiers, lib) = match s.split_once('=') {
+            Some((kind, lib)) => {
+                let (kind, modifiers) = match kind.split_once(':') {
+                    Some((kind, modifiers)) => (kind, Some(modifiers)),
+                    None => (kind, None),
+                };
+
+                (NativeLibKind::try_from(kind)?, modifiers, lib)
+            }
+            None => (NativeLibKind::Unspecified, None, s),
+        };
+
+        let modifiers = modifiers
+            .into_iter()
+            .flat_map(|modifiers| modifiers.split(','))
+            .map(|modifier| {
+                let (enabled, name) = match modifier.split_at(modifier.len().min(1)) {
+                    ("+", name) => (true, name),
+                    ("-", name) => (false, name),
+                    _ => return Err(invalid()),
+                };
+
+                Ok((LinkModifier::try_from(name)?, enabled))
+            })
+            .collect::<Result<_>>()?;
+
+        // Link arguments may contain colons, so they cannot be renamed
+        let (name, rename) = match (kind, lib.split_once(':')) {
+            (NativeLibKind::LinkArg, _) | (_, None) => (lib, None),
+            (_, Some((name, rename))) => (name, Some(rename.to_owned())),
+        };
+
+        if name.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(NativeLib {
+            kind,
+            modifiers,
+            name: name.to_owned(),
+            rename,
+        })
+    }
+}
+
+impl Display for NativeLib {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        if self.kind != NativeLibKind::Unspecified {
+            write!(f, "{}", self.kind)?;
+
+            if !self.modifiers.is_empty() {
+                let modifiers: Vec<String> = self
+                    .modifiers
+                    .iter()
+                    .map(|(modifier, enabled)| {
+                        format!("{}{modifier}", if *enabled { '+' } else { '-' })
+                    })
+                    .collect();
+
+                write!(f, ":{}", modifiers.join(","))?;
+            }
+
+            write!(f, "=")?;
+        }
+
+        write!(f, "{}", self.name)?;
+
+        match &self.rename {
+            Some(rename) => write!(f, ":{rename}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A configuration option given with `--cfg NAME[="VALUE"]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cfg {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+impl TryFrom<&str> for Cfg {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Cfg> {
+        let invalid = || Error::InvalidArg(format!("invalid `--cfg` argument: `{s}`"));
+
+        let (name, value) = match s.split_once('=') {
+            Some((name, value)) => {
+                let quoted = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .ok_or_else(invalid)?;
+
+                (
+                    name.trim(),
+                    Some(quoted.replace("\\\"", "\"").replace("\\\\", "\\")),
+                )
+            }
+            None => (s.trim(), None),
+        };
+
+        let valid_identifier = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+
+        match valid_identifier {
+            true => Ok(Cfg {
+                name: name.to_owned(),
+                value,
+            }),
+            false => Err(invalid()),
+        }
+    }
+}
+
+impl Display for Cfg {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.value {
+            Some(value) => write!(
+                f,
+                "{}=\"{}\"",
+                self.name,
+                value.replace('\\', "\\\\").replace('"', "\\\"")
+            ),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// Values expected for the names of a `--check-cfg` specification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpectedValues {
+    /// Any value, given with `values(any())`
+    Any,
+    /// Listed values. `None` stands for the name without a value, given with `none()`
+    Listed(Vec<Option<String>>),
+}
+
+/// Expected configuration options given with `--check-cfg cfg(NAME..., values(...))`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckCfg {
+    /// Expected names, or `None` if any name is expected using `cfg(any())`
+    pub names: Option<Vec<String>>,
+    pub values: ExpectedValues,
+}
+
+impl CheckCfg {
+    /// Split a comma separated list, ignoring the commas inside of nested parentheses
+    /// and string literals
+    fn split_list(list: &str) -> Option<Vec<&str>> {
+        let mut items = Vec::new();
+        let (mut depth, mut in_string, mut escaped, mut start) = (0usize, false, false, 0);
+
+        for (i, c) in list.char_indices() {
+            match (c, in_string) {
+                (_, true) if escaped => escaped = false,
+                ('\\', true) => escaped = true,
+                ('"', _) => in_string = !in_string,
+                ('(', false) => depth += 1,
+                (')', false) => depth = depth.checked_sub(1)?,
+                (',', false) if depth == 0 => {
+                    items.push(list[start..i].trim());
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        if in_string || depth != 0 {
+            return None;
+        }
+
+        let last = list[start..].trim();
+        match (items.is_empty(), last.is_empty()) {
+            (true, true) => Some(items),
+            (_, false) => {
+                items.push(last);
+                Some(items)
+            }
+            (false, true) => None,
+        }
+    }
+
+    fn parse_value(value: &str) -> Option<Option<String>> {
+        match value {
+            "none()" => Some(None),
+            _ => value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .map(|v| Some(v.replace("\\\"", "\"").replace("\\\\", "\\"))),
+        }
+    }
+
+    fn is_identifier(name: &str) -> bool {
+        let name = name.strip_prefix("r#").unwrap_or(name);
+
+        name.chars()
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+    }
+}
+
+impl TryFrom<&str> for CheckCfg {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<CheckCfg> {
+        let invalid = || Error::InvalidArg(format!("invalid `--check-cfg` argument: `{s}`"));
+
+        let items = s
+            .trim()
+            .strip_prefix("cfg(")
+            .and_then(|spec| spec.strip_suffix(')'))
+            .and_then(CheckCfg::split_list)
+            .ok_or_else(invalid)?;
+
+        let mut check_cfg = CheckCfg {
+            names: Some(Vec::new()),
+            values: ExpectedValues::Listed(vec![None]),
+        };
+        let mut values_given = false;
+
+        for item in items.into_iter() {
+            // The expected values must come last
+            if values_given {
+                return Err(invalid());
+            }
+
+            match (
+                item,
+                item.strip_prefix("values(")
+                    .and_then(|v| v.strip_suffix(')')),
+            ) {
+                ("any()", _) => check_cfg.names = None,
+                (_, Some(values)) => {
+                    let values = CheckCfg::split_list(values).ok_or_else(invalid)?;
+                    check_cfg.values = match values.as_slice() {
+                        ["any()"] => ExpectedValues::Any,
+                        _ => ExpectedValues::Listed(
+                            values
+                                .iter()
+                                .map(|value| CheckCfg::parse_value(value))
+                                .collect::<Option<_>>()
+                                .ok_or_else(invalid)?,
+                        ),
+                    };
+                    values_given = true;
+                }
+                (name, None) if CheckCfg::is_identifier(name) => {
+                    if let Some(names) = check_cfg.names.as_mut() {
+                        names.push(name.to_owned());
+                    }
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(check_cfg)
+    }
+}
+
+impl Display for CheckCfg {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let mut items = match &self.names {
+            Some(names) => names.clone(),
+            None => vec![String::from("any()")],
+        };
+
+        match &self.values {
+            ExpectedValues::Any => items.push(String::from("values(any())")),
+            ExpectedValues::Listed(values) if values.as_slice() == [None] => {}
+            ExpectedValues::Listed(values) => {
+                let values: Vec<String> = values
+                    .iter()
+                    .map(|value| match value {
+                        Some(value) => {
+                            format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+                        }
+                        None => String::from("none()"),
+                    })
+                    .collect();
+                items.push(format!("values({})", values.join(", ")));
+            }
+        }
+
+        write!(f, "cfg({})", items.join(", "))
+    }
+}
+
+/// Lint levels, given either through `-A`/`-W`/`--force-warn`/`-D`/`-F` or
+/// `--cap-lints`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    ForceWarn,
+    Deny,
+    Forbid,
+}
+
+impl LintLevel {
+    /// Command line flag used to set a lint to this level
+    fn flag(&self) -> &'static str {
+        match self {
+            LintLevel::Allow => "-A",
+            LintLevel::Warn => "-W",
+            LintLevel::ForceWarn => "--force-warn",
+            LintLevel::Deny => "-D",
+            LintLevel::Forbid => "-F",
+        }
+    }
+}
+
+impl TryFrom<&str> for LintLevel {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<LintLevel> {
+        match s {
+            "allow" => Ok(LintLevel::Allow),
+            "warn" => Ok(LintLevel::Warn),
+            "force-warn" => Ok(LintLevel::ForceWarn),
+            "deny" => Ok(LintLevel::Deny),
+            "forbid" => Ok(LintLevel::Forbid),
+            _ => Err(Error::InvalidArg(format!("unknown lint level: `{s}`"))),
+        }
+    }
+}
+
+impl Display for LintLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            LintLevel::Allow => "allow",
+            LintLevel::Warn => "warn",
+            LintLevel::ForceWarn => "force-warn",
+            LintLevel::Deny => "deny",
+            LintLevel::Forbid => "forbid",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// A lint, or lint group, set to a specific level on the command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintOption {
+    pub name: String,
+    pub level: LintLevel,
+}
+
+/// Formats accepted by `--error-format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+    Short,
+}
+
+impl TryFrom<&str> for ErrorFormat {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<ErrorFormat> {
+        match s {
+            "human" => Ok(ErrorFormat::Human),
+            "json" => Ok(ErrorFormat::Json),
+            "short" => Ok(ErrorFormat::Short),
+            _ => Err(Error::InvalidArg(format!("unknown error format: `{s}`"))),
+        }
+    }
+}
+
+impl Display for ErrorFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            ErrorFormat::Human => "human",
+            ErrorFormat::Json => "json",
+            ErrorFormat::Short => "short",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// Rendering options accepted by `--json`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonOption {
+    DiagnosticShort,
+    DiagnosticRenderedAnsi,
+    Artifacts,
+    FutureIncompat,
+    UnusedExterns,
+    UnusedExternsSilent,
+}
+
+impl TryFrom<&str> for JsonOption {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<JsonOption> {
+        match s {
+            "diagnostic-short" => Ok(JsonOption::DiagnosticShort),
+            "diagnostic-rendered-ansi" => Ok(JsonOption::DiagnosticRenderedAnsi),
+            "artifacts" => Ok(JsonOption::Artifacts),
+            "future-incompat" => Ok(JsonOption::FutureIncompat),
+            "unused-externs" => Ok(JsonOption::UnusedExterns),
+            "unused-externs-silent" => Ok(JsonOption::UnusedExternsSilent),
+            _ => Err(Error::InvalidArg(format!("unknown `--json` option: `{s}`"))),
+        }
+    }
+}
+
+impl Display for JsonOption {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            JsonOption::DiagnosticShort => "diagnostic-short",
+            JsonOption::DiagnosticRenderedAnsi => "diagnostic-rendered-ansi",
+            JsonOption::Artifacts => "artifacts",
+            JsonOption::FutureIncompat => "future-incompat",
+            JsonOption::UnusedExterns => "unused-externs",
+            JsonOption::UnusedExternsSilent => "unused-externs-silent",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// Rust editions accepted by `--edition`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Edition {
+    E2015,
+    E2018,
+    E2021,
+    E2024,
+}
+
+impl TryFrom<&str> for Edition {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Edition> {
+        match s {
+            "2015" => Ok(Edition::E2015),
+            "2018" => Ok(Edition::E2018),
+            "2021" => Ok(Edition::E2021),
+            "2024" => Ok(Edition::E2024),
+            _ => Err(Error::InvalidArg(format!("unknown edition: `{s}`"))),
+        }
+    }
+}
+
+impl Display for Edition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            Edition::E2015 => "2015",
+            Edition::E2018 => "2018",
+            Edition::E2021 => "2021",
+            Edition::E2024 => "2024",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// Typed representation of a complete `rustc` invocation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RustcInvocation {
+    /// Input file to compile. `-` means the input is read from stdin
+    pub input: Option<String>,
+    pub crate_name: Option<String>,
+    pub crate_types: Vec<CrateType>,
+    pub edition: Option<Edition>,
+    pub emit: Vec<Emit>,
+    pub print: Vec<PrintRequest>,
+    pub out_dir: Option<PathBuf>,
+    /// Output path given with `-o`
+    pub output: Option<PathBuf>,
+    pub target: Option<String>,
+    pub search_paths: Vec<SearchPath>,
+    pub externs: Vec<ExternCrate>,
+    pub native_libs: Vec<NativeLib>,
+    pub cfgs: Vec<Cfg>,
+    pub check_cfgs: Vec<CheckCfg>,
+    pub codegen: CodegenOptions,
+    /// Lint levels, in the order in which they appear on the command line
+    pub lints: Vec<LintOption>,
+    pub cap_lints: Option<LintLevel>,
+    pub error_format: Option<ErrorFormat>,
+    pub json: Vec<JsonOption>,
+    pub color: Option<String>,
+    pub diagnostic_width: Option<usize>,
+    pub remap_path_prefix: Vec<String>,
+    pub sysroot: Option<PathBuf>,
+    pub test: bool,
+    /// Unstable options given with `-Z`
+    pub unstable_options: Vec<String>,
+    pub verbose: bool,
+    pub version: bool,
+}
+
+/// Split comma-separated values given to options which can be repeated
+fn split_values(matches: &Matches, name: &str) -> Vec<String> {
+    matches
+        .opt_strs(name)
+        .iter()
+        .flat_map(|value| value.split(','))
+        .filter(|value| !value.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+impl RustcInvocation {
+    fn options() -> Options {
         let mut options = Options::new();
         options.optopt("", "crate-name", "Name of the crate to compile", "NAME");
         options.optopt("", "edition", "Rust edition to use", "YEAR");
@@ -23,33 +965,586 @@ impl TryFrom<&[String]> for RustcArgs {
             "Directory in which to output generated files",
             "DIR",
         );
-        options.optopt("", "emit", "Requested output to emit", "KIND");
-        options.optopt("", "json", "JSON Rendering type", "RENDER");
+        options.optopt("o", "", "Write output to <filename>", "FILENAME");
+        options.optmulti("", "emit", "Requested output to emit", "KIND[=PATH]");
+        options.optmulti("", "print", "Compiler information to print", "INFO");
+        options.optmulti("", "json", "JSON Rendering type", "RENDER");
         options.optmulti("C", "", "Extra compiler options", "OPTION[=VALUE]");
+        options.optmulti("Z", "", "Unstable compiler options", "OPTION[=VALUE]");
         options.optmulti(
             "L",
             "",
             "Add a directory to the library's search path",
             "KIND[=PATH]",
         );
-        options.optmulti("", "crate-type", "Type of binary to output", "TYPE");
         options.optmulti(
+            "l",
+            "",
+            "Link the generated crate to the specified native library",
+            "[KIND[:MODIFIERS]=]NAME[:RENAME]",
+        );
+        options.optmulti(
+            "",
+            "extern",
+            "Specify where an external crate is",
+            "NAME[=PATH]",
+        );
+        options.optmulti("", "crate-type", "Type of binary to output", "TYPE");
+        options.optopt("", "target", "Target triple to compile for", "TARGET");
+        options.optopt(
             "",
             "cap-lints",
             "Set the most restrictive lint level",
             "LEVEL",
         );
+        options.optmulti("A", "", "Set lint allowed", "LINT");
+        options.optmulti("W", "", "Set lint warnings", "LINT");
+        options.optmulti("", "force-warn", "Set lint force-warn", "LINT");
+        options.optmulti("D", "", "Set lint denied", "LINT");
+        options.optmulti("F", "", "Set lint forbidden", "LINT");
         options.optmulti("", "cfg", "Configure the compilation environment", "SPEC");
+        options.optmulti("", "check-cfg", "Provide list of expected cfgs", "SPEC");
+        options.optopt("", "color", "Configure coloring of output", "WHEN");
+        options.optopt("", "diagnostic-width", "Width of diagnostics", "WIDTH");
+        options.optmulti("", "remap-path-prefix", "Remap source paths", "FROM=TO");
+        options.optopt("", "sysroot", "Override the system root", "PATH");
+        options.optflag("g", "", "Equivalent to -C debuginfo=2");
+        options.optflag("O", "", "Equivalent to -C opt-level=2");
+        options.optflag("", "test", "Build a test harness");
+        options.optflag("v", "verbose", "Use verbose output");
+        options.optflag("V", "version", "Print version info and exit");
 
-        // Parse arguments, skipping `cargo-gccrs` and `rustc` in the invocation
-        Ok(RustcArgs {
-            matches: options.parse(&args[2..])?,
+        options
+    }
+
+    /// Collect all lint levels while keeping their relative order, since later
+    /// options override earlier ones
+    fn lints(matches: &Matches) -> Vec<LintOption> {
+        let mut lints: Vec<(usize, LintOption)> = [
+            ("A", LintLevel::Allow),
+            ("W", LintLevel::Warn),
+            ("force-warn", LintLevel::ForceWarn),
+            ("D", LintLevel::Deny),
+            ("F", LintLevel::Forbid),
+        ]
+        .iter()
+        .flat_map(|(flag, level)| {
+            matches
+                .opt_strs_pos(flag)
+                .into_iter()
+                .map(move |(pos, name)| {
+                    (
+                        pos,
+                        LintOption {
+                            name,
+                            level: *level,
+                        },
+                    )
+                })
         })
+        .collect();
+
+        lints.sort_by_key(|(pos, _)| *pos);
+
+        lints.into_iter().map(|(_, lint)| lint).collect()
+    }
+
+    fn from_matches(matches: Matches) -> Result<RustcInvocation> {
+        if matches.free.len() > 1 {
+            return Err(Error::InvalidArg(format!(
+                "multiple input filenames provided: {}",
+                matches.free.join(", ")
+            )));
+        }
+
+        let mut codegen = CodegenOptions::default();
+        matches
+            .opt_strs("C")
+            .iter()
+            .try_for_each(|option| codegen.apply(option))?;
+
+        if matches.opt_present("g") {
+            codegen.apply("debuginfo=2")?;
+        }
+        if matches.opt_present("O") {
+            codegen.apply("opt-level=2")?;
+        }
+        // `-Z sanitizer` is the unstable spelling of `-C sanitize`
+        for option in matches.opt_strs("Z").iter() {
+            if let Some(sanitizers) = option.strip_prefix("sanitizer=") {
+                codegen.apply(&format!("sanitize={sanitizers}"))?;
+            }
+        }
+
+        let diagnostic_width = matches
+            .opt_str("diagnostic-width")
+            .map(|width| {
+                width
+                    .parse()
+                    .map_err(|_| Error::InvalidArg(format!("invalid diagnostic width: `{width}`")))
+            })
+            .transpose()?;
+
+        Ok(RustcInvocation {
+            input: matches.free.first().cloned(),
+            crate_name: matches.opt_str("crate-name"),
+            crate_types: split_values(&matches, "crate-type")
+                .iter()
+                .map(|s| CrateType::try_from(s.as_str()))
+                .collect::<Result<_>>()?,
+            edition: matches
+                .opt_str("edition")
+                .map(|s| Edition::try_from(s.as_str()))
+                .transpose()?,
+            emit: split_values(&matches, "emit")
+                .iter()
+                .map(|s| Emit::try_from(s.as_str()))
+                .collect::<Result<_>>()?,
+            print: matches
+                .opt_strs("print")
+                .iter()
+                .map(|s| PrintRequest::try_from(s.as_str()))
+                .collect::<Result<_>>()?,
+            out_dir: matches.opt_str("out-dir").map(PathBuf::from),
+            output: matches.opt_str("o").map(PathBuf::from),
+            target: matches.opt_str("target"),
+            search_paths: matches
+                .opt_strs("L")
+                .iter()
+                .map(|s| SearchPath::from(s.as_str()))
+                .collect(),
+            externs: matches
+                .opt_strs("extern")
+                .iter()
+                .map(|s| ExternCrate::try_from(s.as_str()))
+                .collect::<Result<_>>()?,
+            native_libs: matches
+                .opt_strs("l")
+                .iter()
+                .map(|s| NativeLib::try_from(s.as_str()))
+                .collect::<Result<_>>()?,
+            cfgs: matches
+                .opt_strs("cfg")
+                .iter()
+                .map(|s| Cfg::try_from(s.as_str()))
+                .collect::<Result<_>>()?,
+            check_cfgs: matches
+                .opt_strs("check-cfg")
+                .iter()
+                .map(|s| CheckCfg::try_from(s.as_str()))
+                .collect::<Result<_>>()?,
+            codegen,
+            lints: RustcInvocation::lints(&matches),
+            cap_lints: matches
+                .opt_str("cap-lints")
+                .map(|s| LintLevel::try_from(s.as_str()))
+                .transpose()?,
+            error_format: matches
+                .opt_str("error-format")
+                .map(|s| ErrorFormat::try_from(s.as_str()))
+                .transpose()?,
+            json: split_values(&matches, "json")
+                .iter()
+                .map(|s| JsonOption::try_from(s.as_str()))
+                .collect::<Result<_>>()?,
+            color: matches.opt_str("color"),
+            diagnostic_width,
+            remap_path_prefix: matches.opt_strs("remap-path-prefix"),
+            sysroot: matches.opt_str("sysroot").map(PathBuf::from),
+            test: matches.opt_present("test"),
+            unstable_options: matches.opt_strs("Z"),
+            verbose: matches.opt_present("v"),
+            version: matches.opt_present("V"),
+        })
+    }
+
+    /// Get the name of the crate being compiled. Like `rustc`, fall back to the name of
+    /// the input file when no `--crate-name` is given
+    pub fn output_crate_name(&self) -> String {
+        if let Some(crate_name) = &self.crate_name {
+            return crate_name.clone();
+        }
+
+        self.input
+            .as_deref()
+            .filter(|input| *input != "-")
+            .and_then(|input| Path::new(input).file_stem())
+            .and_then(|stem| stem.to_str())
+            .map(|stem| stem.replace('-', "_"))
+            .unwrap_or_else(|| String::from("rust_out"))
+    }
+
+    /// Get the requested crate types. Like `rustc`, default to a binary when no
+    /// `--crate-type` is given
+    pub fn crate_types_or_default(&self) -> Vec<CrateType> {
+        match self.crate_types.is_empty() {
+            true => vec![CrateType::Bin],
+            false => self.crate_types.clone(),
+        }
+    }
+
+    /// Get the configuration options in effect: Those given with `--cfg`, followed by
+    /// the ones implied by other options. `gccrs` is always set, so that crates can
+    /// work around the features it does not support yet
+    pub fn all_cfgs(&self) -> Vec<Cfg> {
+        let mut cfgs = self.cfgs.clone();
+
+        cfgs.push(Cfg {
+            name: String::from("gccrs"),
+            value: None,
+        });
+
+        if self.codegen.debug_assertions_enabled() {
+            cfgs.push(Cfg {
+                name: String::from("debug_assertions"),
+                value: None,
+            });
+        }
+
+        cfgs.push(Cfg {
+            name: String::from("panic"),
+            value: Some(self.codegen.panic_strategy().to_string()),
+        });
+
+        cfgs.extend(self.codegen.sanitizers.iter().map(|sanitizer| Cfg {
+            name: String::from("sanitize"),
+            value: Some(sanitizer.to_string()),
+        }));
+
+        cfgs
+    }
+
+    /// Serialize the invocation back into a list of `rustc` arguments
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+
+        fn push<T: Display>(args: &mut Vec<String>, flag: &str, value: T) {
+            args.push(String::from(flag));
+            args.push(value.to_string());
+        }
+
+        if let Some(input) = &self.input {
+            args.push(input.clone());
+        }
+        if let Some(crate_name) = &self.crate_name {
+            push(&mut args, "--crate-name", crate_name);
+        }
+        for crate_type in self.crate_types.iter() {
+            push(&mut args, "--crate-type", crate_type);
+        }
+        if let Some(edition) = self.edition {
+            push(&mut args, "--edition", edition);
+        }
+        for emit in self.emit.iter() {
+            push(&mut args, "--emit", emit);
+        }
+        for print in self.print.iter() {
+            push(&mut args, "--print", print);
+        }
+        if let Some(out_dir) = &self.out_dir {
+            push(&mut args, "--out-dir", out_dir.display());
+        }
+        if let Some(output) = &self.output {
+            push(&mut args, "-o", output.display());
+        }
+        if let Some(target) = &self.target {
+            push(&mut args, "--target", target);
+        }
+        for search_path in self.search_paths.iter() {
+            push(&mut args, "-L", search_path);
+        }
+        for extern_crate in self.externs.iter() {
+            push(&mut args, "--extern", extern_crate);
+        }
+        for native_lib in self.native_libs.iter() {
+            push(&mut args, "-l", native_lib);
+        }
+        for cfg in self.cfgs.iter() {
+            push(&mut args, "--cfg", cfg);
+        }
+        for check_cfg in self.check_cfgs.iter() {
+            push(&mut args, "--check-cfg", check_cfg);
+        }
+        for codegen_option in self.codegen.to_args() {
+            push(&mut args, "-C", codegen_option);
+        }
+        for lint in self.lints.iter() {
+            push(&mut args, lint.level.flag(), &lint.name);
+        }
+        if let Some(cap_lints) = self.cap_lints {
+            push(&mut args, "--cap-lints", cap_lints);
+        }
+        if let Some(error_format) = self.error_format {
+            push(&mut args, "--error-format", error_format);
+        }
+        if !self.json.is_empty() {
+            let json: Vec<String> = self.json.iter().map(JsonOption::to_string).collect();
+            push(&mut args, "--json", json.join(","));
+        }
+        if let Some(color) = &self.color {
+            push(&mut args, "--color", color);
+        }
+        if let Some(width) = self.diagnostic_width {
+            push(&mut args, "--diagnostic-width", width);
+        }
+        for remap in self.remap_path_prefix.iter() {
+            push(&mut args, "--remap-path-prefix", remap);
+        }
+        if let Some(sysroot) = &self.sysroot {
+            push(&mut args, "--sysroot", sysroot.display());
+        }
+        if self.test {
+            args.push(String::from("--test"));
+        }
+        for unstable_option in self.unstable_options.iter() {
+            push(&mut args, "-Z", unstable_option);
+        }
+        if self.verbose {
+            args.push(String::from("--verbose"));
+        }
+        if self.version {
+            args.push(String::from("--version"));
+        }
+
+        args
     }
 }
 
-impl RustcArgs {
-    pub fn matches(&self) -> &Matches {
-        &self.matches
+/// Parse the arguments given to `rustc`, without the `rustc` binary name
+impl TryFrom<&[String]> for RustcInvocation {
+    type Error = Error;
+
+    fn try_from(args: &[String]) -> Result<Self> {
+        RustcInvocation::from_matches(RustcInvocation::options().parse(args)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen_options::{OptLevel, Sanitizer};
+
+    fn parse(args: &[&str]) -> Result<RustcInvocation> {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+
+        RustcInvocation::try_from(args.as_slice())
+    }
+
+    #[test]
+    fn cargo_invocation() {
+        let invocation = parse(&[
+            "--crate-name",
+            "foo",
+            "--edition=2018",
+            "src/main.rs",
+            "--error-format=json",
+            "--json=diagnostic-rendered-ansi,artifacts,future-incompat",
+            "--crate-type",
+            "bin",
+            "--emit=dep-info,link",
+            "-C",
+            "embed-bitcode=no",
+            "-C",
+            "debuginfo=2",
+            "-C",
+            "metadata=1234",
+            "-C",
+            "extra-filename=-1234",
+            "--out-dir",
+            "/target/debug/deps",
+            "-L",
+            "dependency=/target/debug/deps",
+            "--extern",
+            "bar=/target/debug/deps/libbar-5678.rlib",
+            "--cfg",
+            "feature=\"default\"",
+        ])
+        .unwrap();
+
+        assert_eq!(invocation.input.as_deref(), Some("src/main.rs"));
+        assert_eq!(invocation.crate_types, vec![CrateType::Bin]);
+        assert_eq!(invocation.edition, Some(Edition::E2018));
+        assert_eq!(invocation.codegen.extra_filename.as_deref(), Some("-1234"));
+        assert_eq!(invocation.emit.len(), 2);
+        assert_eq!(invocation.json.len(), 3);
+        assert_eq!(
+            invocation.search_paths,
+            vec![SearchPath {
+                kind: SearchPathKind::Dependency,
+                path: PathBuf::from("/target/debug/deps"),
+            }]
+        );
+        assert_eq!(invocation.externs[0].name, "bar");
+        assert_eq!(
+            invocation.cfgs,
+            vec![Cfg {
+                name: String::from("feature"),
+                value: Some(String::from("default")),
+            }]
+        );
+    }
+
+    #[test]
+    fn check_cfgs() {
+        let invocation = parse(&[
+            "--check-cfg",
+            "cfg(docsrs,test)",
+            "--check-cfg",
+            "cfg(feature, values(\"default\", \"std\", none()))",
+            "--check-cfg",
+            "cfg(any(), values(any()))",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            invocation.check_cfgs,
+            vec![
+                CheckCfg {
+                    names: Some(vec![String::from("docsrs"), String::from("test")]),
+                    values: ExpectedValues::Listed(vec![None]),
+                },
+                CheckCfg {
+                    names: Some(vec![String::from("feature")]),
+                    values: ExpectedValues::Listed(vec![
+                        Some(String::from("default")),
+                        Some(String::from("std")),
+                        None
+                    ]),
+                },
+                CheckCfg {
+                    names: None,
+                    values: ExpectedValues::Any,
+                },
+            ]
+        );
+        assert_eq!(
+            invocation.check_cfgs[1].to_string(),
+            "cfg(feature, values(\"default\", \"std\", none()))"
+        );
+
+        assert!(parse(&["--check-cfg", "names(feature)"]).is_err());
+        assert!(parse(&["--check-cfg", "cfg(values(\"a\"), feature)"]).is_err());
+        assert!(parse(&["--check-cfg", "cfg(feature, values(\"a))"]).is_err());
+        assert!(parse(&["--check-cfg", "cfg(feature,)"]).is_err());
+    }
+
+    #[test]
+    fn lint_order() {
+        let invocation = parse(&["-D", "warnings", "-A", "dead_code", "-W", "unused"]).unwrap();
+        let lints: Vec<(&str, LintLevel)> = invocation
+            .lints
+            .iter()
+            .map(|lint| (lint.name.as_str(), lint.level))
+            .collect();
+
+        assert_eq!(
+            lints,
+            vec![
+                ("warnings", LintLevel::Deny),
+                ("dead_code", LintLevel::Allow),
+                ("unused", LintLevel::Warn)
+            ]
+        );
+    }
+
+    #[test]
+    fn shorthand_flags() {
+        let invocation = parse(&["-O", "-g", "-vV"]).unwrap();
+
+        assert_eq!(invocation.codegen.opt_level, Some(OptLevel::Two));
+        assert!(invocation.version && invocation.verbose);
+    }
+
+    #[test]
+    fn default_crate_type() {
+        let invocation = parse(&["src/main.rs"]).unwrap();
+
+        assert_eq!(invocation.output_crate_name(), "main");
+        assert_eq!(invocation.crate_types_or_default(), vec![CrateType::Bin]);
+    }
+
+    #[test]
+    fn reported_release() {
+        let minor: u32 = RUSTC_RELEASE.split('.').nth(1).unwrap().parse().unwrap();
+
+        // `--edition 2024` was stabilized in Rust 1.85.0
+        assert_eq!(
+            parse(&["--edition", "2024"]).unwrap().edition,
+            Some(Edition::E2024)
+        );
+        assert!(minor >= 85);
+    }
+
+    #[test]
+    fn native_libs() {
+        let invocation = parse(&["-l", "static:+whole-archive,-bundle=foo", "-l", "bar"]).unwrap();
+        let foo = &invocation.native_libs[0];
+
+        assert_eq!(foo.kind, NativeLibKind::Static);
+        assert_eq!(foo.name, "foo");
+        assert_eq!(foo.modifier(LinkModifier::WholeArchive), Some(true));
+        assert_eq!(foo.modifier(LinkModifier::Bundle), Some(false));
+        assert_eq!(foo.modifier(LinkModifier::Verbatim), None);
+        assert_eq!(invocation.native_libs[1].kind, NativeLibKind::Unspecified);
+
+        assert!(parse(&["-l", "static:whole-archive=foo"]).is_err());
+        assert!(parse(&["-l", "archive=foo"]).is_err());
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        assert!(parse(&["--crate-type", "executable"]).is_err());
+        assert!(parse(&["--cfg", "feature=default"]).is_err());
+        assert!(parse(&["a.rs", "b.rs"]).is_err());
+        assert!(parse(&["-C", "unknown"]).is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let invocation = parse(&[
+            "-",
+            "--crate-name",
+            "___",
+            "--print=file-names",
+            "--crate-type",
+            "bin,staticlib",
+            "--emit=obj=out.o",
+            "-L",
+            "native=/usr/lib",
+            "--extern",
+            "priv:baz",
+            "-l",
+            "dylib:+verbatim,-as-needed=libqux.so",
+            "-l",
+            "link-arg=-Wl,-z,now",
+            "--cfg",
+            "unix",
+            "--cfg",
+            "feature=\"a\\\"b\"",
+            "--check-cfg",
+            "cfg(feature, values(\"a\", none()))",
+            "-C",
+            "opt-level=z",
+            "-Z",
+            "sanitizer=address,leak",
+            "-F",
+            "unsafe_code",
+            "--cap-lints",
+            "allow",
+            "--target",
+            "x86_64-unknown-linux-gnu",
+        ])
+        .unwrap();
+
+        let args = invocation.to_args();
+
+        assert_eq!(
+            invocation.codegen.sanitizers,
+            vec![Sanitizer::Address, Sanitizer::Leak]
+        );
+        assert_eq!(
+            RustcInvocation::try_from(args.as_slice()).unwrap(),
+            invocation
+        );
     }
 }
@@ -0,0 +1,93 @@
+//! Translate the sanitizers requested with `-C sanitize` or `-Z sanitizer` into GCC's
+//! `-fsanitize` option. GCC does not implement every sanitizer `rustc` knows about, and
+//! cannot combine some of the ones it implements: Both cases are rejected before
+//! compiling anything.
+
+use super::{codegen_options::Sanitizer, target::Target, Error, Result};
+
+/// Sanitizers which cannot be enabled together
+const INCOMPATIBLE: &[(Sanitizer, Sanitizer)] = &[
+    (Sanitizer::Address, Sanitizer::Thread),
+    (Sanitizer::Address, Sanitizer::HwAddress),
+    (Sanitizer::Address, Sanitizer::KernelAddress),
+    (Sanitizer::HwAddress, Sanitizer::Thread),
+    (Sanitizer::HwAddress, Sanitizer::KernelAddress),
+    (Sanitizer::Leak, Sanitizer::Thread),
+    (Sanitizer::KernelAddress, Sanitizer::Thread),
+];
+
+/// Get the name of a sanitizer for `-fsanitize`, if GCC supports it on the target
+fn gcc_sanitizer(target: &Target, sanitizer: Sanitizer) -> Result<&'static str> {
+    let aarch64_only = |name| match target.arch.as_str() {
+        "aarch64" => Ok(name),
+        _ => Err(Error::Sanitizer(format!(
+            "the `{sanitizer}` sanitizer is only available on `aarch64` targets"
+        ))),
+    };
+
+    match sanitizer {
+        Sanitizer::Address => Ok("address"),
+        Sanitizer::KernelAddress => Ok("kernel-address"),
+        Sanitizer::Leak => Ok("leak"),
+        Sanitizer::Thread => Ok("thread"),
+        Sanitizer::Undefined => Ok("undefined"),
+        Sanitizer::HwAddress => aarch64_only("hwaddress"),
+        Sanitizer::ShadowCallStack => aarch64_only("shadow-call-stack"),
+        _ => Err(Error::Sanitizer(format!(
+            "GCC does not provide the `{sanitizer}` sanitizer"
+        ))),
+    }
+}
+
+/// Get the `-fsanitize` option enabling the requested sanitizers, if any. It must be
+/// given both when compiling and linking, so that GCC links the sanitizers' runtimes
+pub fn sanitize_option(target: &Target, sanitizers: &[Sanitizer]) -> Result<Option<String>> {
+    if let Some((first, second)) = INCOMPATIBLE
+        .iter()
+        .find(|(first, second)| sanitizers.contains(first) && sanitizers.contains(second))
+    {
+        return Err(Error::Sanitizer(format!(
+            "the `{first}` and `{second}` sanitizers cannot be combined"
+        )));
+    }
+
+    let names = sanitizers
+        .iter()
+        .map(|sanitizer| gcc_sanitizer(target, *sanitizer))
+        .collect::<Result<Vec<&str>>>()?;
+
+    match names.is_empty() {
+        true => Ok(None),
+        false => Ok(Some(format!("-fsanitize={}", names.join(",")))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn supported_sanitizers() {
+        let x86 = Target::parse("x86_64-linux-gnu").unwrap();
+        let aarch64 = Target::parse("aarch64-linux-gnu").unwrap();
+
+        assert_eq!(
+            sanitize_option(&x86, &[Sanitizer::Address, Sanitizer::Undefined]).unwrap(),
+            Some(String::from("-fsanitize=address,undefined"))
+        );
+        assert_eq!(
+            sanitize_option(&aarch64, &[Sanitizer::HwAddress]).unwrap(),
+            Some(String::from("-fsanitize=hwaddress"))
+        );
+        assert_eq!(sanitize_option(&x86, &[]).unwrap(), None);
+    }
+
+    #[test]
+    fn invalid_sanitizers() {
+        let x86 = Target::parse("x86_64-linux-gnu").unwrap();
+
+        assert!(sanitize_option(&x86, &[Sanitizer::Memory]).is_err());
+        assert!(sanitize_option(&x86, &[Sanitizer::HwAddress]).is_err());
+        assert!(sanitize_option(&x86, &[Sanitizer::Thread, Sanitizer::Address]).is_err());
+    }
+}
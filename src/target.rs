@@ -0,0 +1,238 @@
+//! Target triples, as understood by both `gcc` and `rustc`. The two compilers do not
+//! spell their triples the same way: `gcc -dumpmachine` might output `x86_64-linux-gnu`
+//! when `rustc` would use `x86_64-unknown-linux-gnu`. This module converts between both
+//! representations.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use super::{rustc_args::CrateType, Error, Result};
+
+/// A target triple, stored using `rustc`'s naming conventions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub arch: String,
+    pub vendor: String,
+    pub os: String,
+    pub env: Option<String>,
+}
+
+/// Operating systems that may appear in a target triple
+const KNOWN_OS: &[&str] = &[
+    "linux",
+    "windows",
+    "darwin",
+    "freebsd",
+    "netbsd",
+    "openbsd",
+    "dragonfly",
+    "android",
+    "ios",
+    "solaris",
+    "illumos",
+    "hermit",
+    "none",
+    "uefi",
+    "wasi",
+    "fuchsia",
+    "haiku",
+];
+
+impl Target {
+    /// Parse a target triple given either by `gcc -dumpmachine` or by `rustc --target`
+    pub fn parse(triple: &str) -> Result<Target> {
+        let invalid = || Error::InvalidArg(format!("invalid target triple: `{triple}`"));
+
+        let parts: Vec<&str> = triple.trim().split('-').collect();
+        let arch = parts
+            .first()
+            .filter(|a| !a.is_empty())
+            .ok_or_else(invalid)?;
+
+        // The vendor is optional in `gcc` triples, so look for the operating system
+        // first in order to know what the other components are
+        let os_position = parts
+            .iter()
+            .skip(1)
+            .position(|part| {
+                KNOWN_OS.iter().any(|os| part.starts_with(os)) || part.starts_with("mingw")
+            })
+            .map(|pos| pos + 1)
+            .ok_or_else(invalid)?;
+
+        let vendor = match os_position {
+            1 => "unknown",
+            _ => parts[1],
+        };
+        let os = parts[os_position];
+        let env = parts.get(os_position + 1).map(|env| env.to_string());
+
+        Ok(Target::normalize(arch, vendor, os, env))
+    }
+
+    /// Convert the components of a `gcc` triple to their `rustc` equivalents
+    fn normalize(arch: &str, vendor: &str, os: &str, env: Option<String>) -> Target {
+        let arch = match arch {
+            "amd64" => "x86_64",
+            "riscv64" => "riscv64gc",
+            "arm64" => "aarch64",
+            arch => arch,
+        };
+
+        let (vendor, os, env) = match (vendor, os) {
+            // MinGW targets are spelled `x86_64-w64-mingw32` by `gcc`
+            (_, os) if os.starts_with("mingw") => ("pc", "windows", Some(String::from("gnu"))),
+            (_, os) if os.starts_with("darwin") => ("apple", "darwin", env),
+            // `gcc` appends the version of the operating system to BSD triples
+            (vendor, os) if os.ends_with(|c: char| c.is_ascii_digit()) => (
+                vendor,
+                os.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.'),
+                env,
+            ),
+            ("pc", "linux") => ("unknown", "linux", env),
+            (vendor, os) => (vendor, os, env),
+        };
+
+        Target {
+            arch: arch.to_owned(),
+            vendor: vendor.to_owned(),
+            os: os.to_owned(),
+            env,
+        }
+    }
+
+    fn is_windows(&self) -> bool {
+        self.os == "windows"
+    }
+
+    fn is_apple(&self) -> bool {
+        self.vendor == "apple"
+    }
+
+    /// Check if the target uses ELF binaries, which carry DWARF debug information
+    pub fn is_elf(&self) -> bool {
+        !self.is_windows() && !self.is_apple()
+    }
+
+    /// Suffix of executables on the target, such as `.exe` on Windows
+    pub fn exe_suffix(&self) -> &'static str {
+        match self.is_windows() {
+            true => ".exe",
+            false => "",
+        }
+    }
+
+    /// Prefix and suffix of shared libraries on the target
+    fn dylib_affixes(&self) -> (&'static str, &'static str) {
+        match (self.is_windows(), self.is_apple()) {
+            (true, _) => ("", ".dll"),
+            (_, true) => ("lib", ".dylib"),
+            _ => ("lib", ".so"),
+        }
+    }
+
+    /// Prefix and suffix of static libraries on the target
+    fn staticlib_affixes(&self) -> (&'static str, &'static str) {
+        match (self.is_windows(), self.env.as_deref()) {
+            (true, Some("msvc")) => ("", ".lib"),
+            _ => ("lib", ".a"),
+        }
+    }
+
+    /// Name of the file produced for a given crate type, following `rustc`'s naming
+    /// conventions. `name` should already contain the crate's extra filename
+    pub fn file_name(&self, crate_type: CrateType, name: &str) -> String {
+        let (prefix, suffix) = match crate_type {
+            CrateType::Bin => ("", self.exe_suffix()),
+            CrateType::Lib | CrateType::Rlib => ("lib", ".rlib"),
+            CrateType::DyLib | CrateType::CDyLib | CrateType::ProcMacro => self.dylib_affixes(),
+            CrateType::StaticLib => self.staticlib_affixes(),
+        };
+
+        format!("{prefix}{name}{suffix}")
+    }
+
+    /// Native libraries that must be linked alongside a static library produced by
+    /// `gccrs`, as reported by `--print native-static-libs`
+    pub fn native_static_libs(&self) -> &'static [&'static str] {
+        match self.os.as_str() {
+            "windows" => &["gcc_eh", "kernel32", "ntdll", "userenv", "ws2_32"],
+            "darwin" => &["System", "c", "m"],
+            _ => &["gcc_s", "c"],
+        }
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}-{}-{}", self.arch, self.vendor, self.os)?;
+
+        match &self.env {
+            Some(env) => write!(f, "-{env}"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Target;
+    use crate::rustc_args::CrateType;
+
+    fn rustc_triple(gcc_triple: &str) -> String {
+        Target::parse(gcc_triple).unwrap().to_string()
+    }
+
+    #[test]
+    fn gcc_triples() {
+        assert_eq!(rustc_triple("x86_64-linux-gnu"), "x86_64-unknown-linux-gnu");
+        assert_eq!(
+            rustc_triple("x86_64-pc-linux-gnu"),
+            "x86_64-unknown-linux-gnu"
+        );
+        assert_eq!(
+            rustc_triple("aarch64-linux-gnu\n"),
+            "aarch64-unknown-linux-gnu"
+        );
+        assert_eq!(
+            rustc_triple("riscv64-linux-gnu"),
+            "riscv64gc-unknown-linux-gnu"
+        );
+        assert_eq!(rustc_triple("x86_64-w64-mingw32"), "x86_64-pc-windows-gnu");
+        assert_eq!(
+            rustc_triple("x86_64-unknown-freebsd13.2"),
+            "x86_64-unknown-freebsd"
+        );
+        assert_eq!(rustc_triple("x86_64-apple-darwin21"), "x86_64-apple-darwin");
+    }
+
+    #[test]
+    fn rustc_triples() {
+        assert_eq!(
+            rustc_triple("x86_64-unknown-linux-musl"),
+            "x86_64-unknown-linux-musl"
+        );
+        assert_eq!(
+            rustc_triple("armv7-unknown-linux-gnueabihf"),
+            "armv7-unknown-linux-gnueabihf"
+        );
+    }
+
+    #[test]
+    fn invalid_triples() {
+        assert!(Target::parse("").is_err());
+        assert!(Target::parse("x86_64").is_err());
+    }
+
+    #[test]
+    fn file_names() {
+        let linux = Target::parse("x86_64-linux-gnu").unwrap();
+        let windows = Target::parse("x86_64-w64-mingw32").unwrap();
+
+        assert_eq!(linux.file_name(CrateType::Bin, "foo"), "foo");
+        assert_eq!(linux.file_name(CrateType::Lib, "foo-1"), "libfoo-1.rlib");
+        assert_eq!(linux.file_name(CrateType::CDyLib, "foo"), "libfoo.so");
+        assert_eq!(windows.file_name(CrateType::Bin, "foo"), "foo.exe");
+        assert_eq!(windows.file_name(CrateType::DyLib, "foo"), "foo.dll");
+        assert_eq!(windows.file_name(CrateType::StaticLib, "foo"), "libfoo.a");
+    }
+}
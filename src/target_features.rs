@@ -0,0 +1,241 @@
+//! Translate `-C target-cpu` and `-C target-feature` into `gcc` machine options. `rustc`
+//! and `gcc` do not always name CPU features the same way, and each architecture has its
+//! own way of selecting them: `x86` uses `-m<feature>` and `-mno-<feature>`, while
+//! `aarch64` appends `+<feature>` and `+no<feature>` modifiers to `-march` or `-mcpu`.
+
+use super::{codegen_options::CodegenOptions, target::Target, Error, Result};
+
+/// Features handled by the driver itself instead of the code generator
+const DRIVER_FEATURES: &[&str] = &["crt-static"];
+
+/// `rustc` features known on `x86` and `x86_64`, followed by their `gcc` name
+const X86_FEATURES: &[(&str, &str)] = &[
+    ("adx", "adx"),
+    ("aes", "aes"),
+    ("avx", "avx"),
+    ("avx2", "avx2"),
+    ("avx512bf16", "avx512bf16"),
+    ("avx512bitalg", "avx512bitalg"),
+    ("avx512bw", "avx512bw"),
+    ("avx512cd", "avx512cd"),
+    ("avx512dq", "avx512dq"),
+    ("avx512f", "avx512f"),
+    ("avx512fp16", "avx512fp16"),
+    ("avx512ifma", "avx512ifma"),
+    ("avx512vbmi", "avx512vbmi"),
+    ("avx512vbmi2", "avx512vbmi2"),
+    ("avx512vl", "avx512vl"),
+    ("avx512vnni", "avx512vnni"),
+    ("avx512vp2intersect", "avx512vp2intersect"),
+    ("avx512vpopcntdq", "avx512vpopcntdq"),
+    ("bmi1", "bmi"),
+    ("bmi2", "bmi2"),
+    ("cmpxchg16b", "cx16"),
+    ("f16c", "f16c"),
+    ("fma", "fma"),
+    ("fxsr", "fxsr"),
+    ("gfni", "gfni"),
+    ("lzcnt", "lzcnt"),
+    ("movbe", "movbe"),
+    ("pclmulqdq", "pclmul"),
+    ("popcnt", "popcnt"),
+    ("rdrand", "rdrnd"),
+    ("rdseed", "rdseed"),
+    ("sha", "sha"),
+    ("sse", "sse"),
+    ("sse2", "sse2"),
+    ("sse3", "sse3"),
+    ("sse4.1", "sse4.1"),
+    ("sse4.2", "sse4.2"),
+    ("sse4a", "sse4a"),
+    ("ssse3", "ssse3"),
+    ("tbm", "tbm"),
+    ("vaes", "vaes"),
+    ("vpclmulqdq", "vpclmulqdq"),
+    ("xsave", "xsave"),
+    ("xsavec", "xsavec"),
+    ("xsaveopt", "xsaveopt"),
+    ("xsaves", "xsaves"),
+];
+
+/// `rustc` features known on `aarch64`, followed by their `gcc` modifier
+const AARCH64_FEATURES: &[(&str, &str)] = &[
+    ("aes", "aes"),
+    ("bf16", "bf16"),
+    ("crc", "crc"),
+    ("dotprod", "dotprod"),
+    ("fhm", "fp16fml"),
+    ("fp", "fp"),
+    ("fp16", "fp16"),
+    ("i8mm", "i8mm"),
+    ("lse", "lse"),
+    ("mte", "memtag"),
+    ("neon", "simd"),
+    ("rand", "rng"),
+    ("rcpc", "rcpc"),
+    ("rdm", "rdma"),
+    ("sb", "sb"),
+    ("sha2", "sha2"),
+    ("sha3", "sha3"),
+    ("sm4", "sm4"),
+    ("ssbs", "ssbs"),
+    ("sve", "sve"),
+    ("sve2", "sve2"),
+];
+
+/// Architecture families which select CPU features differently
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Family {
+    X86,
+    AArch64,
+}
+
+impl Family {
+    fn of(target: &Target) -> Option<Family> {
+        match target.arch.as_str() {
+            "x86_64" | "i386" | "i586" | "i686" => Some(Family::X86),
+            "aarch64" => Some(Family::AArch64),
+            _ => None,
+        }
+    }
+
+    fn features(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Family::X86 => X86_FEATURES,
+            Family::AArch64 => AARCH64_FEATURES,
+        }
+    }
+}
+
+/// Get the `gcc` name of a `rustc` target feature
+fn gcc_feature(target: &Target, family: Option<Family>, name: &str) -> Result<&'static str> {
+    family
+        .and_then(|family| {
+            family
+                .features()
+                .iter()
+                .find(|(rustc, _)| *rustc == name)
+                .map(|(_, gcc)| *gcc)
+        })
+        .ok_or_else(|| {
+            Error::InvalidArg(format!(
+                "unknown target feature `{name}` for target `{target}`"
+            ))
+        })
+}
+
+/// Translate the target CPU and features requested with `-C` into `gcc` options
+pub fn machine_args(target: &Target, codegen: &CodegenOptions) -> Result<Vec<String>> {
+    let family = Family::of(target);
+
+    let mut features = Vec::new();
+    for feature in codegen
+        .target_features
+        .iter()
+        .filter(|feature| !DRIVER_FEATURES.contains(&feature.name.as_str()))
+    {
+        features.push((gcc_feature(target, family, &feature.name)?, feature.enabled));
+    }
+
+    let cpu = codegen.target_cpu.as_deref();
+
+    let args = match family {
+        Some(Family::X86) => {
+            let cpu_args = match cpu {
+                None => vec![],
+                Some("generic") => vec![String::from("-mtune=generic")],
+                Some(cpu @ "native") => vec![format!("-march={cpu}")],
+                Some(cpu) => vec![format!("-march={cpu}"), format!("-mtune={cpu}")],
+            };
+
+            cpu_args
+                .into_iter()
+                .chain(features.iter().map(|(name, enabled)| match enabled {
+                    true => format!("-m{name}"),
+                    false => format!("-mno-{name}"),
+                }))
+                .collect()
+        }
+        // `aarch64` has no switch per feature, only modifiers of the selected CPU or
+        // architecture. Default to the baseline `rustc` uses
+        Some(Family::AArch64) if features.is_empty() => cpu
+            .map(|cpu| vec![format!("-mcpu={cpu}")])
+            .unwrap_or_default(),
+        Some(Family::AArch64) => {
+            let base = match cpu {
+                Some(cpu) => format!("-mcpu={cpu}"),
+                None => String::from("-march=armv8-a"),
+            };
+
+            vec![features
+                .iter()
+                .fold(base, |base, (name, enabled)| match enabled {
+                    true => format!("{base}+{name}"),
+                    false => format!("{base}+no{name}"),
+                })]
+        }
+        // Features were already rejected above. The CPU name is given as is to other
+        // architectures, which mostly use `-mcpu`
+        None => cpu
+            .map(|cpu| vec![format!("-mcpu={cpu}")])
+            .unwrap_or_default(),
+    };
+
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codegen(options: &[&str]) -> CodegenOptions {
+        let mut codegen = CodegenOptions::default();
+        options.iter().for_each(|opt| codegen.apply(opt).unwrap());
+
+        codegen
+    }
+
+    #[test]
+    fn x86_features() {
+        let target = Target::parse("x86_64-linux-gnu").unwrap();
+        let codegen = codegen(&[
+            "target-cpu=skylake",
+            "target-feature=+avx2,-sse4.1,+crt-static,+pclmulqdq",
+        ]);
+
+        assert_eq!(
+            machine_args(&target, &codegen).unwrap(),
+            vec![
+                "-march=skylake",
+                "-mtune=skylake",
+                "-mavx2",
+                "-mno-sse4.1",
+                "-mpclmul"
+            ]
+        );
+    }
+
+    #[test]
+    fn aarch64_features() {
+        let target = Target::parse("aarch64-linux-gnu").unwrap();
+
+        assert_eq!(
+            machine_args(&target, &codegen(&["target-feature=+lse,-neon"])).unwrap(),
+            vec!["-march=armv8-a+lse+nosimd"]
+        );
+        assert_eq!(
+            machine_args(&target, &codegen(&["target-cpu=native"])).unwrap(),
+            vec!["-mcpu=native"]
+        );
+    }
+
+    #[test]
+    fn unknown_features() {
+        let x86 = Target::parse("x86_64-linux-gnu").unwrap();
+        let riscv = Target::parse("riscv64-linux-gnu").unwrap();
+
+        assert!(machine_args(&x86, &codegen(&["target-feature=+neon"])).is_err());
+        assert!(machine_args(&riscv, &codegen(&["target-feature=+zba"])).is_err());
+        assert!(machine_args(&riscv, &codegen(&["target-feature=+crt-static"])).is_ok());
+    }
+}
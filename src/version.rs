@@ -0,0 +1,121 @@
+//! Emulate the output of `rustc --version` and `rustc -vV`. `cargo` uses the verbose
+//! version to find out the host triple, and hashes the entire output in its
+//! fingerprints: Changing the version of `gccrs` thus causes a full rebuild.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use super::{rustc_args::RUSTC_RELEASE, target::Target, Error, Result};
+
+/// Version information about the `gccrs` compiler, formatted like `rustc`'s own
+pub struct VersionInfo {
+    /// First line of `gccrs --version`
+    gcc_version: String,
+    /// GCC release, as a semantic version
+    gcc_release: String,
+    commit_date: Option<String>,
+    host: Target,
+}
+
+impl VersionInfo {
+    /// Create a new [`VersionInfo`] from the first line of `gccrs --version` and the
+    /// output of `gcc -dumpmachine`
+    pub fn new(gccrs_version: &str, machine: &str) -> Result<VersionInfo> {
+        let gcc_version = gccrs_version.lines().next().unwrap_or_default().trim();
+
+        // `gccrs (GCC) 14.0.1 20240311 (experimental)`: The release is the first
+        // dotted number, and the commit date is the optional one following it
+        let mut words = gcc_version
+            .split_whitespace()
+            .skip_while(|word| !VersionInfo::is_release(word));
+        let release = words.next().ok_or(Error::InvalidVersion)?;
+        let commit_date = words
+            .next()
+            .filter(|word| word.len() == 8 && word.chars().all(|c| c.is_ascii_digit()))
+            .map(|date| format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..]));
+
+        Ok(VersionInfo {
+            gcc_version: gcc_version.to_owned(),
+            gcc_release: VersionInfo::semver(release),
+            commit_date,
+            host: Target::parse(machine)?,
+        })
+    }
+
+    fn is_release(word: &str) -> bool {
+        word.contains('.')
+            && word
+                .split('.')
+                .all(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+    }
+
+    /// Turn GCC releases such as `15.2` into a semantic version, with three components
+    fn semver(release: &str) -> String {
+        let mut components: Vec<&str> = release.split('.').take(3).collect();
+        components.resize(3, "0");
+
+        components.join(".")
+    }
+
+    /// Get the line printed by `rustc --version`
+    pub fn short(&self) -> String {
+        format!("rustc {RUSTC_RELEASE} (gccrs {})", self.gcc_release)
+    }
+}
+
+/// Format the version like `rustc -vV` does. The `LLVM version` line is replaced by the
+/// GCC release and the complete `gccrs` version
+impl Display for VersionInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        writeln!(f, "{}", self.short())?;
+        writeln!(f, "binary: rustc")?;
+        writeln!(f, "commit-hash: unknown")?;
+        writeln!(
+            f,
+            "commit-date: {}",
+            self.commit_date.as_deref().unwrap_or("unknown")
+        )?;
+        writeln!(f, "host: {}", self.host)?;
+        writeln!(f, "release: {RUSTC_RELEASE}")?;
+        writeln!(f, "GCC release: {}", self.gcc_release)?;
+        write!(f, "GCC version: {}", self.gcc_version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VersionInfo;
+
+    #[test]
+    fn experimental_version() {
+        let version = VersionInfo::new(
+            "gccrs (GCC) 14.0.1 20240311 (experimental)\nCopyright (C) 2024",
+            "x86_64-linux-gnu",
+        )
+        .unwrap();
+
+        assert_eq!(
+            version.to_string(),
+            "rustc 1.85.0 (gccrs 14.0.1)\n\
+             binary: rustc\n\
+             commit-hash: unknown\n\
+             commit-date: 2024-03-11\n\
+             host: x86_64-unknown-linux-gnu\n\
+             release: 1.85.0\n\
+             GCC release: 14.0.1\n\
+             GCC version: gccrs (GCC) 14.0.1 20240311 (experimental)"
+        );
+    }
+
+    #[test]
+    fn release_version() {
+        let version = VersionInfo::new("gccrs (Debian 15.2-1) 15.2", "aarch64-linux-gnu").unwrap();
+
+        assert_eq!(version.short(), "rustc 1.85.0 (gccrs 15.2.0)");
+        assert!(version.to_string().contains("commit-date: unknown"));
+    }
+
+    #[test]
+    fn invalid_version() {
+        assert!(VersionInfo::new("gccrs: command not found", "x86_64-linux-gnu").is_err());
+    }
+}
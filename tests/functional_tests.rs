@@ -11,6 +11,8 @@ mod tests {
         Harness::check_folder("binary_project", FileType::Bin).unwrap();
         Harness::check_folder("static_lib", FileType::Static).unwrap();
         Harness::check_folder("shared_library", FileType::Dyn).unwrap();
+        Harness::check_folder("rust_lib", FileType::Rlib).unwrap();
+        Harness::check_folder("path_dependency", FileType::Bin).unwrap();
 
         // FIXME: As of right now, this just fails on rustc compilation which is not what
         // we want to check
@@ -20,4 +22,14 @@ mod tests {
         assert!(Harness::check_folder("warning_project", FileType::Bin).is_err());
         std::env::set_var("GCCRS_EXTRA_ARGS", "");
     }
+
+    #[test]
+    fn pipelined_library_warnings() {
+        // Libraries are compiled in two passes when pipelining: The unused variable
+        // must still be reported by one of them
+        let diagnostics = Harness::gccrs_diagnostics("warning_lib").unwrap();
+
+        assert!(diagnostics.contains("warning"));
+        assert!(diagnostics.contains("unused"));
+    }
 }
@@ -0,0 +1,7 @@
+pub fn foo() {}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn it_works() {}
+}
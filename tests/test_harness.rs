@@ -1,4 +1,7 @@
-use goblin::{elf64::header::ET_DYN, Object};
+use goblin::{
+    elf64::header::{ET_DYN, ET_EXEC},
+    Object,
+};
 use is_executable::IsExecutable;
 use tempdir::TempDir;
 
@@ -18,6 +21,8 @@ use std::{
 pub enum FileType {
     /// Static libraries
     Static,
+    /// Rust libraries
+    Rlib,
     /// Dynamic libraries
     Dyn,
     /// Binary executables
@@ -27,6 +32,18 @@ pub enum FileType {
 pub struct Harness;
 
 impl Harness {
+    /// Tweak the path so that the most recently compiled *debug* version of cargo-gccrs
+    /// is available as a subcommand to projects in the `tests` directory
+    fn gccrs_path() -> OsString {
+        // Create this target path in a way that's compatible with Windows.
+        let target_path = PathBuf::from("..").join("..").join("target").join("debug");
+
+        let mut paths = vec![target_path];
+        paths.append(&mut env::split_paths(&env::var("PATH").unwrap()).collect::<Vec<_>>());
+
+        join_paths(paths.iter()).unwrap()
+    }
+
     /// Build the project present in the current directory using `rustc` or `gccrs`
     fn cargo_build(use_gccrs: bool, target_dir: Option<&TempDir>) -> Result<()> {
         let mut cmd = Command::new("cargo");
@@ -35,18 +52,7 @@ impl Harness {
         if use_gccrs {
             // Add `gccrs` argument so that `cargo build` becomes `cargo gccrs build`
             cmd.arg("gccrs");
-
-            // Tweak the path so that the most recently compiled *debug* version of
-            // cargo-gccrs is available as a subcommand.
-            // Create this target path in a way that's compatible with Windows.
-            let target_path = PathBuf::from("..").join("..").join("target").join("debug");
-
-            let mut paths = vec![target_path];
-            paths.append(&mut env::split_paths(&env::var("PATH").unwrap()).collect::<Vec<_>>());
-
-            let new_path = join_paths(paths.iter()).unwrap();
-
-            cmd.env("PATH", new_path);
+            cmd.env("PATH", Harness::gccrs_path());
         }
 
         cmd.arg("build");
@@ -76,10 +82,24 @@ impl Harness {
         Ok(())
     }
 
+    /// Executables are position independent unless a relocation model without position
+    /// independent code is given to the compiler
+    fn executable_type() -> u16 {
+        let rustflags = env::var("RUSTFLAGS").unwrap_or_default();
+
+        match rustflags.contains("relocation-model=static")
+            || rustflags.contains("relocation-model=dynamic-no-pic")
+        {
+            true => ET_EXEC,
+            false => ET_DYN,
+        }
+    }
+
     fn check_correct_filetype(file: &Path, expected_type: &FileType) -> Result<()> {
         let e_type = match expected_type {
-            FileType::Static => return Harness::check_archive(file),
-            FileType::Dyn | FileType::Bin => ET_DYN,
+            FileType::Static | FileType::Rlib => return Harness::check_archive(file),
+            FileType::Dyn => ET_DYN,
+            FileType::Bin => Harness::executable_type(),
         };
 
         let elf_data = std::fs::read(file)?;
@@ -95,6 +115,7 @@ impl Harness {
         // FIXME: Wrong on windows
         let predicate = match file_type {
             FileType::Static => |p: &Path| p.extension() == Some(OsString::from("a")).as_deref(),
+            FileType::Rlib => |p: &Path| p.extension() == Some(OsString::from("rlib")).as_deref(),
             FileType::Dyn => |p: &Path| p.extension() == Some(OsString::from("so")).as_deref(),
             FileType::Bin => |p: &Path| p.is_executable(),
         };
@@ -159,6 +180,26 @@ impl Harness {
         Ok(())
     }
 
+    /// Build the project in a given folder using `gccrs`, and get the diagnostics
+    /// reported while building it
+    pub fn gccrs_diagnostics(folder_path: &str) -> Result<String> {
+        let gccrs_target_tmpdir = TempDir::new(&format!("{}-target-gccrs", folder_path))?;
+
+        let output = Command::new("cargo")
+            .args(["gccrs", "build", "--target-dir"])
+            .arg(gccrs_target_tmpdir.path())
+            // Other tests change the current directory
+            .current_dir(
+                Path::new(env!("CARGO_MANIFEST_DIR"))
+                    .join("tests")
+                    .join(folder_path),
+            )
+            .env("PATH", Harness::gccrs_path())
+            .output()?;
+
+        Ok(String::from_utf8_lossy(&output.stderr).into_owned())
+    }
+
     /// Runs the folder generic test suite on a give folder. This test suite
     /// makes sure that the project compiles using `rustc` as well as `gccrs`,
     /// before verifying that both compilers output create binaires with the
@@ -0,0 +1,5 @@
+pub fn answer() -> i32 {
+    let unused = 1;
+
+    42
+}